@@ -9,7 +9,8 @@ fn get_context() -> VMContextBuilder {
     let mut builder = VMContextBuilder::new();
     builder
         .predecessor_account_id(ValidAccountId::try_from("user.near").unwrap())
-        .current_account_id(ValidAccountId::try_from("contract.near").unwrap());
+        .current_account_id(ValidAccountId::try_from("contract.near").unwrap())
+        .attached_deposit(1_000_000_000_000_000_000_000_000); // 1 NEAR covers token creation
     builder
 }
 
@@ -30,9 +31,10 @@ fn test_token_creation() {
         expires_at: None,
         starts_at: None,
         extra: None,
+        symbol: None,
     };
 
-    let token_id = contract.create_token(metadata);
+    let token_id = contract.create_token("ipfs://content".to_string(), metadata, None, None);
     let stored_token = contract.get_token(token_id).unwrap();
     assert_eq!(stored_token.metadata.title, "Test Token");
     assert_eq!(stored_token.metadata.copies, Some(1000));
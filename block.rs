@@ -21,6 +21,60 @@ pub enum BlockPhase {
     Finished,
 }
 
+// How equal-vote tokens at the winner cutoff are ordered
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TieBreak {
+    OldestFirst,
+    Random,
+}
+
+// Which end of the token queue `start_block` takes tokens from
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum QueueOrder {
+    Fifo,  // oldest first, so nothing starves under `max_tokens_per_block`
+    Lifo,
+}
+
+// Default phase durations for new blocks; a zero `public_duration` skips
+// the public phase so voting flows straight into completion
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockPhaseConfig {
+    pub accepting_tokens_duration: u64,
+    pub voting_duration: u64,
+    pub public_duration: u64,
+}
+
+impl Default for BlockPhaseConfig {
+    fn default() -> Self {
+        Self {
+            accepting_tokens_duration: ACCEPTING_TOKENS_DURATION,
+            voting_duration: VOTING_DURATION,
+            public_duration: PUBLIC_DURATION,
+        }
+    }
+}
+
+// How a stake converts into votes. Quadratic applies to each voter's
+// combined stake on a token, so splitting a stake buys nothing extra.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VotingMode {
+    Linear,
+    Quadratic,
+}
+
+impl VotingMode {
+    pub fn power(&self, stake: Balance) -> Balance {
+        match self {
+            VotingMode::Linear => stake,
+            VotingMode::Quadratic => Math::sqrt(stake),
+        }
+    }
+}
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Block {
     pub start_time: u64,
@@ -33,6 +87,7 @@ pub struct Block {
     pub total_stakes: Balance,
     pub phase: BlockPhase,
     pub voting_end_time: u64, // Added field
+    pub voting_extended: bool,  // low participation already bought a grace period
 }
 
 impl Block {
@@ -56,6 +111,7 @@ impl Block {
             total_stakes: 0,
             phase: BlockPhase::AcceptingTokens,
             voting_end_time,
+            voting_extended: false,
         }
     }
 
@@ -63,10 +119,27 @@ impl Block {
         self.tokens.push(token_id);
     }
 
-    pub fn update_phase(&mut self, current_time: u64) {
+    // Timestamps at which the accepting, voting and public phases end
+    pub fn phase_boundaries(&self) -> (u64, u64, u64) {
         let accepting_end = self.start_time + self.accepting_tokens_duration;
         let voting_end = accepting_end + self.voting_duration;
         let public_end = voting_end + self.public_duration;
+        (accepting_end, voting_end, public_end)
+    }
+
+    // Reopens voting until `grace` after `current_time`, once per block; the
+    // public phase moves back with it
+    pub fn extend_voting(&mut self, grace: u64, current_time: u64) {
+        assert!(!self.voting_extended, "Voting already extended");
+        let (accepting_end, _, _) = self.phase_boundaries();
+        self.voting_end_time = current_time + grace;
+        self.voting_duration = self.voting_end_time - accepting_end;
+        self.voting_extended = true;
+        self.update_phase(current_time);
+    }
+
+    pub fn update_phase(&mut self, current_time: u64) {
+        let (accepting_end, voting_end, public_end) = self.phase_boundaries();
 
         self.phase = if current_time < accepting_end {
             BlockPhase::AcceptingTokens
@@ -79,6 +152,17 @@ impl Block {
         };
     }
 
+    // Timestamp at which the current phase ends
+    pub fn phase_end_time(&self) -> u64 {
+        let (accepting_end, voting_end, public_end) = self.phase_boundaries();
+
+        match self.phase {
+            BlockPhase::AcceptingTokens => accepting_end,
+            BlockPhase::Voting => voting_end,
+            BlockPhase::Public | BlockPhase::Completed => public_end,
+        }
+    }
+
     pub fn is_accepting_tokens(&self, current_time: u64) -> bool {
         matches!(self.phase, BlockPhase::AcceptingTokens)
             && current_time < self.start_time + self.accepting_tokens_duration
@@ -97,6 +181,39 @@ impl Block {
     }
 }
 
+// Layout of `Block` before `voting_extended`, read by `migrate`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldBlock {
+    pub start_time: u64,
+    pub accepting_tokens_duration: u64,
+    pub voting_duration: u64,
+    pub public_duration: u64,
+    pub min_stake: Balance,
+    pub max_winners: u8,
+    pub tokens: Vec<TokenId>,
+    pub total_stakes: Balance,
+    pub phase: BlockPhase,
+    pub voting_end_time: u64,
+}
+
+impl From<OldBlock> for Block {
+    fn from(old: OldBlock) -> Self {
+        Self {
+            start_time: old.start_time,
+            accepting_tokens_duration: old.accepting_tokens_duration,
+            voting_duration: old.voting_duration,
+            public_duration: old.public_duration,
+            min_stake: old.min_stake,
+            max_winners: old.max_winners,
+            tokens: old.tokens,
+            total_stakes: old.total_stakes,
+            phase: old.phase,
+            voting_end_time: old.voting_end_time,
+            voting_extended: false,
+        }
+    }
+}
+
 // Add BlockView
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -123,15 +240,35 @@ impl From<&Block> for BlockView {
             max_winners: block.max_winners,
             tokens: block.tokens.clone(),
             total_stakes: U128(block.total_stakes),
-            phase: match block.phase {
-                BlockPhase::AcceptingTokens => "AcceptingTokens".to_string(),
-                BlockPhase::Voting => "Voting".to_string(),
-                BlockPhase::Public => "Public".to_string(),
-                BlockPhase::Completed => "Completed".to_string(),
-                BlockPhase::Priority => "Priority".to_string(),
-            },
+            phase: phase_name(&block.phase),
         }
     }
 }
 
+pub fn phase_name(phase: &BlockPhase) -> String {
+    match phase {
+        BlockPhase::AcceptingTokens => "AcceptingTokens".to_string(),
+        BlockPhase::Voting => "Voting".to_string(),
+        BlockPhase::Public => "Public".to_string(),
+        BlockPhase::Completed => "Completed".to_string(),
+        BlockPhase::Priority => "Priority".to_string(),
+    }
+}
+
+// What a call to `update_block_phase` did
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PhaseUpdateResult {
+    NoBlock,
+    Unchanged(String),
+    Transitioned { from: String, to: String },
+}
 
+// Everything a dashboard needs about the active block in one response
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockSummaryView {
+    pub block: BlockView,
+    pub standings: Vec<(TokenId, U128)>,
+    pub time_remaining_in_phase: u64,
+}
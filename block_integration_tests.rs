@@ -11,7 +11,7 @@ fn setup_test_context() -> VMContextBuilder {
         .predecessor_account_id(ValidAccountId::try_from("user.near").unwrap())
         .current_account_id(ValidAccountId::try_from("contract.near").unwrap())
         .block_timestamp(0)
-        .attached_deposit(0);
+        .attached_deposit(1_000_000_000_000_000_000_000_000); // 1 NEAR covers token creation
     context
 }
 
@@ -33,13 +33,14 @@ fn test_block_lifecycle() {
         expires_at: None,
         starts_at: None,
         extra: None,
+        symbol: None,
     };
 
-    let token_id = contract.create_token(metadata);
+    let token_id = contract.create_token("ipfs://content".to_string(), metadata, None, None);
     assert!(contract.get_queued_tokens().contains(&token_id));
 
     // Start block
-    contract.start_block();
+    contract.start_block(None, None, None);
     let block = contract.get_current_block().unwrap();
     assert_eq!(block.tokens.len(), 1);
     assert_eq!(block.phase, "AcceptingTokens");
@@ -90,9 +91,10 @@ fn test_token_creation() {
         expires_at: None,
         starts_at: None,
         extra: None,
+        symbol: None,
     };
 
-    let token_id = contract.create_token(metadata);
+    let token_id = contract.create_token("ipfs://content".to_string(), metadata, None, None);
     let stored_token = contract.get_token(token_id).unwrap();
     assert_eq!(stored_token.metadata.title, "Test Token");
 }
\ No newline at end of file
@@ -1,45 +1,220 @@
-use near_sdk::{env, near_bindgen, AccountId, Balance, Promise};
+use near_sdk::{env, near_bindgen, AccountId, Balance, Promise, Timestamp};
+use near_sdk::serde_json::json;
 use crate::*;
 
+// Upper bound on tokens per `create_tokens` call to stay within gas
+const MAX_BATCH_SIZE: usize = 20;
+// Upper bound on tokens returned by `get_recent_tokens`
+const MAX_RECENT_TOKENS: u64 = 50;
+// `get_tokens_sorted` only sorts this many of the newest tokens, keeping
+// its gas bounded as the catalog grows
+const MAX_SORTABLE_TOKENS: u64 = 500;
+
+// Placeholders sized for the worst case when estimating creation storage
+const MAX_ACCOUNT_ID_LEN: usize = 64;
+const ESTIMATED_CONTENT_HASH_LEN: usize = 128;
+// Map keys, index entries and the queue slot stored alongside a token
+const TOKEN_STORAGE_OVERHEAD: u64 = 200;
+// A creator's first `creation_log` entry: the account key in the index and
+// key vector plus a one-timestamp log
+const CREATION_LOG_STORAGE: u64 = 2 * (4 + MAX_ACCOUNT_ID_LEN as u64) + 100;
+// A `token_bonds` entry: id key in the index and key vector plus the bond
+const TOKEN_BOND_STORAGE: u64 = 100;
+
 #[near_bindgen]
 impl TokenBlocks {
+    /// Queues a new token and returns its id. Ids come from a monotonically
+    /// increasing counter, so they are never reused, even across blocks;
+    /// `get_next_token_id` predicts the id the next call will assign.
+    /// With an identity verifier set, the id is reserved here and the token
+    /// is only stored once `on_create_verified` sees the creator approved.
     #[payable]
     pub fn create_token(
         &mut self,
         content_hash: String,
         metadata: TokenMetadata,
+        supply_schedule: Option<SupplySchedule>,
+        creator_fee_bps: Option<u32>,
     ) -> TokenId {
-        // Ensure sufficient payment for platform fee
+        assert!(!self.pause_flags.creation, "Token creation is paused");
+        // Ensure sufficient payment for platform fee and bond
         let deposit = env::attached_deposit();
+        let fee = self.platform_fee + self.creator_bond;
         assert!(
-            deposit >= self.platform_fee,
+            deposit >= fee,
             "Insufficient deposit for token creation"
         );
+        let creator = env::predecessor_account_id();
+
+        if self.identity_verifier.is_some() {
+            // Fail fast rather than after the verifier round trip
+            let problems = self.creation_problems(&creator, &content_hash, &metadata, supply_schedule.as_ref(), creator_fee_bps);
+            if let Some(problem) = problems.first() {
+                env::panic_str(problem);
+            }
+            let token_id = self.next_token_id();
+            self.verify_identity(
+                &creator,
+                "on_create_verified",
+                json!({
+                    "creator": creator,
+                    "token_id": token_id,
+                    "content_hash": content_hash,
+                    "metadata": metadata,
+                    "supply_schedule": supply_schedule,
+                    "creator_fee_bps": creator_fee_bps,
+                    "deposit": U128(deposit),
+                }),
+            );
+            return token_id;
+        }
 
-        // Basic validation
-        assert!(!content_hash.is_empty(), "Content hash cannot be empty");
-        assert!(!metadata.title.is_empty(), "Token must have a title");
+        let initial_storage = env::storage_usage();
 
-        // Create new token
+        let token_id = self.next_token_id();
+        self.internal_create_token(token_id, &creator, content_hash, metadata, supply_schedule, creator_fee_bps);
+
+        self.charge_creation(&creator, deposit, fee, initial_storage);
+
+        token_id
+    }
+
+    /// Stores a token whose id `create_token` reserved while its creator was
+    /// being verified, or refunds the deposit if the verifier said no. The
+    /// reserved id is skipped in that case, and also when creation stopped
+    /// being allowed in the meantime (say, a duplicate content hash landed,
+    /// the creator hit the daily limit or the fee went up).
+    #[private]
+    pub fn on_create_verified(
+        &mut self,
+        creator: AccountId,
+        token_id: TokenId,
+        content_hash: String,
+        metadata: TokenMetadata,
+        supply_schedule: Option<SupplySchedule>,
+        creator_fee_bps: Option<u32>,
+        deposit: U128,
+    ) -> bool {
+        let rejected = !Self::identity_verified()
+            || !self.creation_problems(&creator, &content_hash, &metadata, supply_schedule.as_ref(), creator_fee_bps).is_empty()
+            || deposit.0 < self.creation_cost(&creator, &content_hash, &metadata, supply_schedule.clone());
+        if rejected {
+            Promise::new(creator).transfer(deposit.0);
+            return false;
+        }
+
+        let fee = self.platform_fee + self.creator_bond;
+        let initial_storage = env::storage_usage();
+        self.internal_create_token(token_id, &creator, content_hash, metadata, supply_schedule, creator_fee_bps);
+        self.charge_creation(&creator, deposit.0, fee, initial_storage);
+
+        true
+    }
+
+    #[payable]
+    pub fn create_tokens(
+        &mut self,
+        items: Vec<(String, TokenMetadata)>,
+    ) -> Vec<TokenId> {
+        assert!(!self.pause_flags.creation, "Token creation is paused");
+        assert!(
+            self.identity_verifier.is_none(),
+            "Batch creation is unavailable while identity checks are on"
+        );
+        assert!(!items.is_empty(), "No tokens to create");
+        assert!(items.len() <= MAX_BATCH_SIZE, "Too many tokens in batch");
+
+        // One platform fee and bond per token, paid from a single deposit
+        let deposit = env::attached_deposit();
+        let total_fee = (self.platform_fee + self.creator_bond) * items.len() as Balance;
+        assert!(
+            deposit >= total_fee,
+            "Insufficient deposit for token creation"
+        );
+
+        let creator = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+
+        let token_ids = items.into_iter()
+            .map(|(content_hash, metadata)| {
+                let token_id = self.next_token_id();
+                self.internal_create_token(token_id, &creator, content_hash, metadata, None, None);
+                token_id
+            })
+            .collect();
+
+        self.charge_creation(&creator, deposit, total_fee, initial_storage);
+
+        token_ids
+    }
+
+    // Charges `fee` plus the storage used since `initial_storage`, refunding
+    // whatever is left of the deposit to `payer`
+    fn charge_creation(&self, payer: &AccountId, deposit: Balance, fee: Balance, initial_storage: u64) {
+        let storage_used = env::storage_usage() - initial_storage;
+        let required = fee + storage_used as Balance * Storage::STORAGE_PRICE_PER_BYTE;
+        assert!(
+            deposit >= required,
+            "Insufficient deposit for token creation"
+        );
+
+        // Refund excess deposit
+        if deposit > required {
+            Promise::new(payer.clone()).transfer(deposit - required);
+        }
+    }
+
+    // Hands out the next token id; ids are never reused, even when a
+    // reserved id ends up without a token
+    fn next_token_id(&mut self) -> TokenId {
         let token_id = self.token_counter;
-        let token = Token::new(
+        self.token_counter += 1;
+        token_id
+    }
+
+    // Validates and stores a token under an id from `next_token_id`, adding
+    // it to the current block while that block accepts tokens and is under
+    // `max_tokens_per_block`, and to the queue otherwise; fee handling is
+    // left to the caller
+    fn internal_create_token(
+        &mut self,
+        token_id: TokenId,
+        creator: &AccountId,
+        content_hash: String,
+        metadata: TokenMetadata,
+        supply_schedule: Option<SupplySchedule>,
+        creator_fee_bps: Option<u32>,
+    ) {
+        let creator_fee_bps = creator_fee_bps.unwrap_or(0);
+        assert!(creator_fee_bps <= MAX_CREATOR_FEE_BPS, "Creator fee too high");
+        if let Some(problem) = self.token_problems(&content_hash, &metadata).first() {
+            env::panic_str(problem);
+        }
+        let supply_schedule = supply_schedule.unwrap_or(SupplySchedule::Fixed(DEFAULT_WINNER_SUPPLY));
+        self.assert_schedule_within_max(&supply_schedule, metadata.copies);
+
+        self.record_creation(creator);
+
+        // Create new token
+        let mut token = Token::new(
             token_id,
-            env::predecessor_account_id(),
+            creator.clone(),
             content_hash,
             metadata,
+            supply_schedule,
         );
+        token.creator_fee_bps = creator_fee_bps;
+        token.platform_fee_paid = self.platform_fee;
 
-        // Store token and update queue
         self.tokens.insert(&token_id, &token);
-        self.token_queue.push(token_id);
-        self.token_counter += 1;
-
-        // Refund excess deposit
-        if deposit > self.platform_fee {
-            Promise::new(env::predecessor_account_id()).transfer(deposit - self.platform_fee);
+        if self.can_join_current_block() {
+            self.current_block.as_mut().unwrap().add_token(token_id);
+        } else {
+            self.token_queue.push(token_id);
+        }
+        if self.creator_bond > 0 {
+            self.token_bonds.insert(&token_id, &self.creator_bond);
         }
-
-        token_id
     }
 
     // Internal method to process queued tokens into next block
@@ -65,27 +240,423 @@ impl TokenBlocks {
         self.platform_fee = new_fee.0;
     }
 
+    // Admin function to set the decimals reported in token metadata
+    pub fn set_ft_decimals(&mut self, decimals: u8) {
+        self.assert_owner();
+        self.ft_decimals = decimals;
+    }
+
+    // Admin function to require a non-empty description on new tokens
+    pub fn set_require_description(&mut self, required: bool) {
+        self.assert_owner();
+        self.require_description = required;
+    }
+
+    // Admin function to require media on new tokens
+    pub fn set_require_media(&mut self, required: bool) {
+        self.assert_owner();
+        self.require_media = required;
+    }
+
+    /// Native owed to a creator and not yet claimed: their share of winning
+    /// stakes, refunded bonds and native swap fee cuts
+    pub fn get_creator_pending(&self, creator: AccountId) -> U128 {
+        U128(self.creator_pending.get(&creator).unwrap_or(0))
+    }
+
+    /// Pays out everything owed to the caller as a creator
+    pub fn claim_creator_funds(&mut self) -> U128 {
+        let creator = env::predecessor_account_id();
+        let amount = self.creator_pending.remove(&creator)
+            .expect("No pending creator funds");
+        self.total_creator_pending -= amount;
+        Promise::new(creator).transfer(amount);
+        U128(amount)
+    }
+
+    pub(crate) fn credit_creator(&mut self, creator: &AccountId, amount: Balance) {
+        let owed = self.creator_pending.get(creator).unwrap_or(0);
+        self.creator_pending.insert(creator, &(owed + amount));
+        self.total_creator_pending += amount;
+    }
+
+    // Admin function to set the bond creators post with each token
+    pub fn set_creator_bond(&mut self, creator_bond: U128) {
+        self.assert_owner();
+        self.creator_bond = creator_bond.0;
+    }
+
+    // Admin function to cap token creations per account per day (0 = unlimited)
+    pub fn set_creation_rate_limit(&mut self, max_per_day: u32) {
+        self.assert_owner();
+        self.max_tokens_per_account_per_day = max_per_day;
+    }
+
+    // Admin function to cap the supply any winner can be minted
+    pub fn set_max_token_supply(&mut self, max_token_supply: U128) {
+        self.assert_owner();
+        assert!(max_token_supply.0 > 0, "Max supply must be positive");
+        self.max_token_supply = max_token_supply.0;
+    }
+
+    // Admin function to update the minimum voting stake
+    pub fn update_min_stake(&mut self, new_min_stake: U128) {
+        self.assert_owner();
+        self.min_stake = new_min_stake.0;
+    }
+
+    // Owner functions to manage the admin set. Admins can pause and
+    // moderate; every other setting stays with the owner.
+    pub fn add_admin(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.admins.insert(&account_id);
+    }
+
+    pub fn remove_admin(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.admins.remove(&account_id);
+    }
+
+    // Admin function to pause or resume trading, voting, creation and
+    // purchases independently
+    pub fn set_pause_flags(&mut self, flags: PauseFlags) {
+        self.assert_admin();
+        self.pause_flags = flags;
+    }
+
+    pub fn get_pause_flags(&self) -> PauseFlags {
+        self.pause_flags
+    }
+
+    /// Returns the account's role. The owner is reported as `Owner` even
+    /// if it was also added as an admin.
+    pub fn get_role(&self, account_id: AccountId) -> Role {
+        if account_id == self.owner_id {
+            Role::Owner
+        } else if self.admins.contains(&account_id) {
+            Role::Admin
+        } else {
+            Role::User
+        }
+    }
+
+    // Admin functions for content moderation
+    pub fn block_hash(&mut self, hash: String) {
+        self.assert_admin();
+        self.blocked_hashes.insert(&hash);
+    }
+
+    pub fn unblock_hash(&mut self, hash: String) {
+        self.assert_admin();
+        self.blocked_hashes.remove(&hash);
+    }
+
+    // Takes down an existing token; trading on it is rejected from then on
+    pub fn remove_token(&mut self, token_id: TokenId) {
+        self.assert_admin();
+        let mut token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        token.status = TokenStatus::Removed;
+        self.tokens.insert(&token_id, &token);
+
+        // Free its queue or block slot. Once voting has opened the token
+        // stays in the block so its voters are refunded at finalization.
+        self.token_queue.retain(|&queued| queued != token_id);
+        if let Some(ref mut block) = self.current_block {
+            if block.phase == BlockPhase::AcceptingTokens {
+                block.tokens.retain(|&id| id != token_id);
+            }
+        }
+    }
+
+    /// Dry run of `create_token`'s checks; lists every problem, empty if
+    /// the token would be accepted
+    pub fn validate_token(&self, content_hash: String, metadata: TokenMetadata) -> Vec<String> {
+        self.token_problems(&content_hash, &metadata)
+    }
+
+    pub fn is_hash_blocked(&self, hash: String) -> bool {
+        self.blocked_hashes.contains(&hash)
+    }
+
     // View methods
+
+    /// Deposit to attach to `create_token` for this metadata: the platform
+    /// fee, the creator bond and a worst-case estimate of the storage the
+    /// token occupies
+    pub fn get_creation_cost(&self, metadata: TokenMetadata) -> U128 {
+        U128(self.creation_cost(
+            &AccountId::try_from("a".repeat(MAX_ACCOUNT_ID_LEN)).unwrap(),
+            &"x".repeat(ESTIMATED_CONTENT_HASH_LEN),
+            &metadata,
+            None,
+        ))
+    }
+
+    // Fee, bond and estimated storage cost of creating this token
+    fn creation_cost(
+        &self,
+        creator: &AccountId,
+        content_hash: &str,
+        metadata: &TokenMetadata,
+        supply_schedule: Option<SupplySchedule>,
+    ) -> Balance {
+        let token = Token::new(
+            self.token_counter,
+            creator.clone(),
+            content_hash.to_string(),
+            metadata.clone(),
+            supply_schedule.unwrap_or(SupplySchedule::Fixed(DEFAULT_WINNER_SUPPLY)),
+        );
+        // Side writes `internal_create_token` makes besides the token itself
+        let mut overhead = TOKEN_STORAGE_OVERHEAD;
+        if self.max_tokens_per_account_per_day > 0 {
+            overhead += CREATION_LOG_STORAGE;
+        }
+        if self.creator_bond > 0 {
+            overhead += TOKEN_BOND_STORAGE;
+        }
+        let storage_cost = Storage::calculate_required_storage(&token, overhead);
+
+        self.platform_fee + self.creator_bond + storage_cost
+    }
+
+    /// Bond held with each new token, refunded if it wins
+    pub fn get_required_bond(&self) -> U128 {
+        U128(self.creator_bond)
+    }
+
+    pub fn get_next_token_id(&self) -> TokenId {
+        self.token_counter
+    }
+
+    /// Whether a token created now joins the current block rather than
+    /// waiting in the queue for the next one
+    pub fn can_join_current_block(&self) -> bool {
+        self.current_block.as_ref().map_or(false, |block| {
+            self.cancelling.is_none()
+                && block.is_accepting_tokens(env::block_timestamp())
+                && block.tokens.len() < self.max_tokens_per_block as usize
+        })
+    }
+
     pub fn get_token(&self, token_id: TokenId) -> Option<TokenView> {
         self.tokens.get(&token_id).map(|token| (&token).into())
     }
 
-    pub fn get_tokens_by_creator(&self, creator: AccountId) -> Vec<TokenView> {
-        self.tokens
+    /// Nanoseconds since the token was created
+    pub fn get_token_age(&self, token_id: TokenId) -> Option<u64> {
+        self.tokens.get(&token_id)
+            .map(|token| env::block_timestamp().saturating_sub(token.created_at))
+    }
+
+    /// Most recently created tokens first, at most `MAX_RECENT_TOKENS`
+    pub fn get_recent_tokens(&self, limit: u64) -> Vec<TokenView> {
+        (0..self.token_counter)
+            .rev()
+            .filter_map(|token_id| self.tokens.get(&token_id))
+            .take(limit.min(MAX_RECENT_TOKENS) as usize)
+            .map(|token| (&token).into())
+            .collect()
+    }
+
+    /// A page of tokens ordered by `sort_by`, ties broken by id. Only the
+    /// newest `MAX_SORTABLE_TOKENS` tokens are considered.
+    pub fn get_tokens_sorted(
+        &self,
+        sort_by: SortKey,
+        descending: bool,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<TokenView> {
+        let mut keyed: Vec<(Balance, Token)> = (0..self.token_counter)
+            .rev()
+            .filter_map(|token_id| self.tokens.get(&token_id))
+            .take(MAX_SORTABLE_TOKENS as usize)
+            .map(|token| (self.sort_value(&token, sort_by), token))
+            .collect();
+
+        keyed.sort_by(|(a, token_a), (b, token_b)| {
+            let by_key = if descending { b.cmp(a) } else { a.cmp(b) };
+            by_key.then(token_a.id.cmp(&token_b.id))
+        });
+        keyed.iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(_, token)| token.into())
+            .collect()
+    }
+
+    pub fn get_token_balance(&self, account_id: AccountId, token_id: TokenId) -> U128 {
+        let balance = self.token_balances.get(&account_id)
+            .and_then(|balances| balances.get(&token_id).copied())
+            .unwrap_or(0);
+        U128(balance)
+    }
+
+    pub fn ft_metadata_of(&self, token_id: TokenId) -> FungibleTokenMetadata {
+        self.tokens.get(&token_id)
+            .expect("Token not found")
+            .ft_metadata(self.ft_decimals)
+    }
+
+    pub fn get_supply_info(&self, token_id: TokenId) -> SupplyInfo {
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+
+        let circulating_ratio = if token.total_supply == 0 {
+            0
+        } else {
+            Math::calculate_share(token.circulating_supply, token.total_supply, Math::PRECISION)
+        };
+
+        SupplyInfo {
+            total_supply: U128(token.total_supply),
+            circulating_supply: U128(token.circulating_supply),
+            pool_reserve: U128(token.pool_reserve),
+            circulating_ratio: U128(circulating_ratio),
+        }
+    }
+
+    /// The schedule a token's supply is minted by, with the realized total
+    /// once it has won and been minted
+    pub fn get_supply_schedule(&self, token_id: TokenId) -> SupplyScheduleView {
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+
+        SupplyScheduleView {
+            total_supply: (token.total_supply > 0).then(|| U128(token.total_supply)),
+            schedule: token.supply_schedule,
+        }
+    }
+
+    /// Every token by `creator` that won its block, oldest first
+    pub fn get_creator_wins(&self, creator: AccountId) -> Vec<TokenView> {
+        self.creator_wins.get(&creator)
+            .unwrap_or_default()
             .iter()
-            .filter(|(_, token)| token.creator == creator)
-            .map(|(_, token)| (&token).into())
+            .filter_map(|token_id| self.tokens.get(token_id))
+            .map(|token| (&token).into())
             .collect()
     }
 
+    /// Every token by `creator`, in id order
+    pub fn get_tokens_by_creator(&self, creator: AccountId) -> Vec<TokenView> {
+        let mut tokens: Vec<Token> = self.tokens
+            .values()
+            .filter(|token| token.creator == creator)
+            .collect();
+        tokens.sort_by_key(|token| token.id);
+        tokens.iter().map(|token| token.into()).collect()
+    }
+
     // Helper methods
-    fn assert_owner(&self) {
+
+    fn sort_value(&self, token: &Token, sort_by: SortKey) -> Balance {
+        match sort_by {
+            SortKey::CreatedAt => token.created_at as Balance,
+            SortKey::MarketCap => self.get_market_cap(token.id).0,
+            SortKey::Volume24h => self.pools.get(&token.id)
+                .map(|pool| pool.volume_24h)
+                .unwrap_or(0),
+            SortKey::Votes => self.votes.get(&token.id)
+                .map(|vote_info| vote_info.total_votes)
+                .unwrap_or(0),
+        }
+    }
+
+    // Everything `create_token` would reject about this token
+    fn token_problems(&self, content_hash: &str, metadata: &TokenMetadata) -> Vec<String> {
+        let mut problems = Validation::content_hash_problems(content_hash);
+        problems.extend(Validation::metadata_problems(metadata, self.require_description, self.require_media));
+        if self.blocked_hashes.contains(&content_hash.to_string()) {
+            problems.push("Content is blocked".to_string());
+        }
+        if let Some(ref media) = metadata.media {
+            if self.blocked_hashes.contains(media) {
+                problems.push("Media is blocked".to_string());
+            }
+        }
+        problems
+    }
+
+    // Every reason `creator` can't create this token right now, token
+    // validation included. Fees and storage are checked separately.
+    fn creation_problems(
+        &self,
+        creator: &AccountId,
+        content_hash: &str,
+        metadata: &TokenMetadata,
+        supply_schedule: Option<&SupplySchedule>,
+        creator_fee_bps: Option<u32>,
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.pause_flags.creation {
+            problems.push("Token creation is paused".to_string());
+        }
+        if creator_fee_bps.unwrap_or(0) > MAX_CREATOR_FEE_BPS {
+            problems.push("Creator fee too high".to_string());
+        }
+        problems.extend(self.token_problems(content_hash, metadata));
+        if let Some(schedule) = supply_schedule {
+            if !self.schedule_within_max(schedule, metadata.copies) {
+                problems.push("Supply schedule exceeds max supply".to_string());
+            }
+        }
+        let limit = self.max_tokens_per_account_per_day;
+        if limit > 0 && self.creations_today(creator).len() >= limit as usize {
+            problems.push("Daily token creation limit reached".to_string());
+        }
+        problems
+    }
+
+    // Rejects schedules that exceed `max_token_supply` before any voter is
+    // counted; growth past it is clamped when the token wins
+    fn assert_schedule_within_max(&self, schedule: &SupplySchedule, copies: Option<u64>) {
+        assert!(self.schedule_within_max(schedule, copies), "Supply schedule exceeds max supply");
+    }
+
+    fn schedule_within_max(&self, schedule: &SupplySchedule, copies: Option<u64>) -> bool {
+        schedule.total_supply(copies, 0) <= self.max_token_supply
+    }
+
+    // Enforces the per-account daily creation limit, keeping only today's
+    // creation times for each account
+    fn record_creation(&mut self, creator: &AccountId) {
+        let limit = self.max_tokens_per_account_per_day;
+        if limit == 0 {
+            return;
+        }
+        let mut today = self.creations_today(creator);
+        assert!(today.len() < limit as usize, "Daily token creation limit reached");
+        today.push(env::block_timestamp());
+        self.creation_log.insert(creator, &today);
+    }
+
+    // The account's logged creation times that fall on today
+    fn creations_today(&self, creator: &AccountId) -> Vec<Timestamp> {
+        let now = env::block_timestamp();
+        self.creation_log.get(creator)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|&created_at| Time::is_same_day(created_at, now))
+            .collect()
+    }
+
+    pub(crate) fn assert_owner(&self) {
         assert_eq!(
             env::predecessor_account_id(),
             self.owner_id,
             "Only contract owner can call this method"
         );
     }
+
+    pub(crate) fn assert_admin(&self) {
+        assert!(
+            self.get_role(env::predecessor_account_id()) != Role::User,
+            "Only the owner or an admin can call this method"
+        );
+    }
 }
 
 #[cfg(test)]
@@ -116,11 +687,14 @@ mod tests {
             expires_at: None,
             starts_at: None,
             extra: None,
+            symbol: None,
         };
 
         let token_id = contract.create_token(
             "ipfs://test".to_string(),
             metadata,
+            None,
+            None,
         );
 
         assert_eq!(token_id, 0);
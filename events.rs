@@ -0,0 +1,44 @@
+// utils/events.rs
+
+use near_sdk::env;
+use near_sdk::serde_json::{json, Value};
+
+pub const EVENT_STANDARD: &str = "tokenblocks";
+pub const EVENT_VERSION: &str = "1.0.0";
+
+pub struct Events;
+
+impl Events {
+    // Logs a NEP-297 event: EVENT_JSON:{standard, version, event, data}
+    pub fn emit(event: &str, data: Value) {
+        let event = json!({
+            "standard": EVENT_STANDARD,
+            "version": EVENT_VERSION,
+            "event": event,
+            "data": [data],
+        });
+        env::log_str(&format!("EVENT_JSON:{}", event));
+    }
+
+    pub fn phase_changed(from: &str, to: &str, block_start_time: u64) {
+        Self::emit("phase_changed", json!({
+            "from": from,
+            "to": to,
+            "block_start_time": block_start_time.to_string(),
+        }));
+    }
+
+    pub fn voting_extended(block_start_time: u64, voting_end_time: u64) {
+        Self::emit("voting_extended", json!({
+            "block_start_time": block_start_time.to_string(),
+            "voting_end_time": voting_end_time.to_string(),
+        }));
+    }
+
+    pub fn result_callback_failed(receiver: &str, winners: &[u64]) {
+        Self::emit("result_callback_failed", json!({
+            "receiver": receiver,
+            "winners": winners,
+        }));
+    }
+}
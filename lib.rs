@@ -1,22 +1,35 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::UnorderedMap;
-use near_sdk::{env, near_bindgen, AccountId, Balance, PanicOnDefault, Promise};
+use near_sdk::collections::{UnorderedMap, UnorderedSet, Vector};
+use near_sdk::{env, near_bindgen, AccountId, Balance, PanicOnDefault, Promise, Timestamp};
 use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 pub mod models;
 pub use crate::models::{
-    Token, TokenId, TokenMetadata, TokenStatus,
-    Block, BlockPhase, BlockView, 
-    VoteInfo, StakeInfo,
-    TokenView,
+    Token, TokenId, TokenMetadata, TokenStatus, OldToken, OldTokenMetadata, SupplySchedule,
+    FungibleTokenMetadata, DEFAULT_FT_DECIMALS, DEFAULT_WINNER_SUPPLY, MAX_SYMBOL_LENGTH,
+    Block, OldBlock, BlockPhase, BlockPhaseConfig, BlockView, BlockSummaryView, PhaseUpdateResult, QueueOrder, TieBreak, VotingMode, phase_name,
+    VoteInfo, OldVoteInfo, StakeInfo, Distribution, Cancellation, PendingRefund, PositionEntry, PositionKind, PaymentConfig, PauseFlags, Role,
+    TokenView, SupplyInfo, SupplyScheduleView, SortKey, Pool, TradeRecord,
 };
 
+pub const CONTRACT_VERSION: &str = "1.1.0";
 pub const ACCEPTING_TOKENS_DURATION: u64 = 60_000_000_000; // 1 minute
 pub const VOTING_DURATION: u64 = 120_000_000_000; // 2 minutes
 pub const BLOCK_DURATION: u64 = 300_000_000_000; // 5 minutes in nanoseconds
 const PUBLIC_DURATION: u64 = 120_000_000_000; // 2 minutes
 const MIN_STAKE_AMOUNT: Balance = 1_000_000_000_000_000_000_000; // 1 NEAR
 const MAX_WINNERS: u8 = 10;
+const SUMMARY_STANDINGS_LIMIT: usize = 10;
+const DEFAULT_MAX_TOKENS_PER_BLOCK: u32 = 100;
+const PLATFORM_FEE: Balance = 100_000_000_000_000_000_000_000; // 0.1 NEAR
+const DEFAULT_ADD_LIQUIDITY_TOLERANCE_BPS: u32 = 1; // 0.01%
+const DEFAULT_REFUND_BATCH_SIZE: u32 = 50;
+const DEFAULT_POOL_SEED_BPS: u32 = 5_000; // 50%
+const DEFAULT_MAX_TOKEN_SUPPLY: Balance = 1_000_000_000_000;
+const DEFAULT_REFUND_EXPIRY_NS: u64 = 30 * 86_400_000_000_000; // 30 days
+const DEFAULT_MIN_INITIAL_LIQUIDITY: Balance = 1_000_000_000_000_000_000_000_000; // 1 NEAR
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
@@ -29,6 +42,145 @@ pub struct TokenBlocks {
     pub votes: UnorderedMap<TokenId, VoteInfo>,
     pub stakes: UnorderedMap<AccountId, StakeInfo>,
     pub min_stake: Balance,
+    pub version: String,
+    pub platform_fee: Balance,
+    pub pools: UnorderedMap<TokenId, Pool>,
+    pub blocked_hashes: UnorderedSet<String>,
+    pub tie_break: TieBreak,
+    pub lp_positions: UnorderedMap<u64, LpPosition>,
+    pub position_counter: u64,
+    pub lp_balances: UnorderedMap<AccountId, BTreeMap<TokenId, Balance>>,
+    pub add_liquidity_tolerance_bps: u32,
+    pub stake_token: Option<AccountId>,
+    pub pending_distribution: Option<Distribution>,
+    pub block_cooldown_ns: u64,
+    pub last_block_end: Option<u64>,
+    pub max_tokens_per_block: u32,
+    pub token_balances: UnorderedMap<AccountId, BTreeMap<TokenId, Balance>>,
+    pub min_initial_liquidity: Balance,
+    pub voting_reward_pool: Balance,
+    pub winners: Vector<TokenId>,
+    pub ft_decimals: u8,
+    pub phases: BlockPhaseConfig,
+    pub refund_batch_size: u32,
+    pub max_tokens_per_account_per_day: u32,
+    pub creation_log: UnorderedMap<AccountId, Vec<Timestamp>>,
+    pub voting_mode: VotingMode,
+    pub pool_seed_bps: u32,
+    pub max_token_supply: Balance,
+    pub treasury_id: AccountId,
+    pub pending_refunds: UnorderedMap<AccountId, PendingRefund>,
+    pub refund_expiry_ns: u64,
+    pub total_pool_native: Balance,
+    pub total_pending_refunds: Balance,
+    pub protocol_fee_bps: u32,
+    pub trade_history: UnorderedMap<AccountId, Vec<TradeRecord>>,
+    pub min_refund_amount: Balance,
+    pub accepted_payment_tokens: UnorderedMap<AccountId, PaymentConfig>,
+    pub refund_winner_stakes: bool,
+    pub standings_blackout_ns: u64,
+    pub creator_bond: Balance,
+    pub token_bonds: UnorderedMap<TokenId, Balance>,
+    pub stake_weighted_priority: bool,
+    pub priority_purchases: UnorderedMap<AccountId, BTreeMap<TokenId, Balance>>,
+    pub keeper_reward: Balance,
+    pub require_description: bool,
+    pub require_media: bool,
+    pub creator_pending: UnorderedMap<AccountId, Balance>,
+    pub total_creator_pending: Balance,
+    pub identity_verifier: Option<AccountId>,
+    pub allow_self_vote: bool,
+    pub result_callback: Option<AccountId>,
+    pub cancelling: Option<Cancellation>,
+    pub priority_purchase_cooldown_ns: u64,
+    pub last_priority_purchase: UnorderedMap<AccountId, BTreeMap<TokenId, Timestamp>>,
+    pub min_reserve_ratio_bps: u32,
+    pub creator_lp_positions: UnorderedMap<TokenId, u64>,
+    pub creator_wins: UnorderedMap<AccountId, Vec<TokenId>>,
+    pub early_vote_bonus_bps: u32,
+    pub queue_order: QueueOrder,
+    pub min_participation: Balance,
+    pub voting_grace_ns: u64,
+    pub fee_burn_bps: u32,
+    pub admins: UnorderedSet<AccountId>,
+    pub min_pool_liquidity_for_purchase: Balance,
+    pub withdraw_penalty_bps: u32,
+    pub pause_flags: PauseFlags,
+    pub keeper_fund: Balance,
+}
+
+// Where the contract's native balance sits
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceBreakdown {
+    pub account_balance: U128,
+    pub pool_native: U128,
+    pub pending_refunds: U128,
+    pub creator_pending: U128,
+    pub keeper_fund: U128,
+    pub free: U128,
+}
+
+// Snapshot of every tunable contract parameter
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractConfig {
+    pub version: String,
+    pub min_stake: U128,
+    pub platform_fee: U128,
+    pub accepting_tokens_duration: u64,
+    pub voting_duration: u64,
+    pub public_duration: u64,
+    pub max_winners: u8,
+    pub tie_break: TieBreak,
+    pub add_liquidity_tolerance_bps: u32,
+    pub stake_token: Option<AccountId>,
+    pub block_cooldown_ns: u64,
+    pub max_tokens_per_block: u32,
+    pub min_initial_liquidity: U128,
+    pub ft_decimals: u8,
+    pub refund_batch_size: u32,
+    pub max_tokens_per_account_per_day: u32,
+    pub voting_mode: VotingMode,
+    pub pool_seed_bps: u32,
+    pub max_token_supply: U128,
+    pub treasury_id: AccountId,
+    pub refund_expiry_ns: u64,
+    pub protocol_fee_bps: u32,
+    pub min_refund_amount: U128,
+    pub refund_winner_stakes: bool,
+    pub standings_blackout_ns: u64,
+    pub creator_bond: U128,
+    pub stake_weighted_priority: bool,
+    pub keeper_reward: U128,
+    pub require_description: bool,
+    pub require_media: bool,
+    pub identity_verifier: Option<AccountId>,
+    pub allow_self_vote: bool,
+    pub result_callback: Option<AccountId>,
+    pub priority_purchase_cooldown_ns: u64,
+    pub min_reserve_ratio_bps: u32,
+    pub early_vote_bonus_bps: u32,
+    pub queue_order: QueueOrder,
+    pub min_participation: U128,
+    pub voting_grace_ns: u64,
+    pub fee_burn_bps: u32,
+    pub min_pool_liquidity_for_purchase: U128,
+    pub withdraw_penalty_bps: u32,
+    pub pause_flags: PauseFlags,
+}
+
+// Layout of `TokenBlocks` as deployed before versioning, read by `migrate`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldTokenBlocks {
+    pub owner_id: AccountId,
+    pub token_counter: TokenId,
+    pub tokens: UnorderedMap<TokenId, OldToken>,
+    pub current_block: Option<OldBlock>,
+    pub token_queue: Vec<TokenId>,
+    pub votes: UnorderedMap<TokenId, OldVoteInfo>,
+    pub stakes: UnorderedMap<AccountId, StakeInfo>,
+    pub min_stake: Balance,
 }
 
 #[near_bindgen]
@@ -36,7 +188,7 @@ impl TokenBlocks {
     #[init]
     pub fn new(owner_id: String) -> Self {
         Self {
-            owner_id: AccountId::try_from(owner_id).unwrap(),
+            owner_id: AccountId::try_from(owner_id.clone()).unwrap(),
             token_counter: 0,
             tokens: UnorderedMap::new(b"t"),
             current_block: None,
@@ -44,175 +196,544 @@ impl TokenBlocks {
             votes: UnorderedMap::new(b"v"),
             stakes: UnorderedMap::new(b"s"),
             min_stake: MIN_STAKE_AMOUNT,
+            version: CONTRACT_VERSION.to_string(),
+            platform_fee: PLATFORM_FEE,
+            pools: UnorderedMap::new(b"p"),
+            blocked_hashes: UnorderedSet::new(b"b"),
+            tie_break: TieBreak::OldestFirst,
+            lp_positions: UnorderedMap::new(b"l"),
+            position_counter: 0,
+            lp_balances: UnorderedMap::new(b"m"),
+            add_liquidity_tolerance_bps: DEFAULT_ADD_LIQUIDITY_TOLERANCE_BPS,
+            stake_token: None,
+            pending_distribution: None,
+            block_cooldown_ns: 0,
+            last_block_end: None,
+            max_tokens_per_block: DEFAULT_MAX_TOKENS_PER_BLOCK,
+            token_balances: UnorderedMap::new(b"w"),
+            min_initial_liquidity: DEFAULT_MIN_INITIAL_LIQUIDITY,
+            voting_reward_pool: 0,
+            winners: Vector::new(b"h"),
+            ft_decimals: DEFAULT_FT_DECIMALS,
+            phases: BlockPhaseConfig::default(),
+            refund_batch_size: DEFAULT_REFUND_BATCH_SIZE,
+            max_tokens_per_account_per_day: 0,
+            creation_log: UnorderedMap::new(b"c"),
+            voting_mode: VotingMode::Linear,
+            pool_seed_bps: DEFAULT_POOL_SEED_BPS,
+            max_token_supply: DEFAULT_MAX_TOKEN_SUPPLY,
+            treasury_id: AccountId::try_from(owner_id.clone()).unwrap(),
+            pending_refunds: UnorderedMap::new(b"r"),
+            refund_expiry_ns: DEFAULT_REFUND_EXPIRY_NS,
+            total_pool_native: 0,
+            total_pending_refunds: 0,
+            protocol_fee_bps: 0,
+            trade_history: UnorderedMap::new(b"x"),
+            min_refund_amount: 0,
+            accepted_payment_tokens: UnorderedMap::new(b"a"),
+            refund_winner_stakes: false,
+            standings_blackout_ns: 0,
+            creator_bond: 0,
+            token_bonds: UnorderedMap::new(b"o"),
+            stake_weighted_priority: false,
+            priority_purchases: UnorderedMap::new(b"q"),
+            keeper_reward: 0,
+            require_description: false,
+            require_media: false,
+            creator_pending: UnorderedMap::new(b"k"),
+            total_creator_pending: 0,
+            identity_verifier: None,
+            allow_self_vote: true,
+            result_callback: None,
+            cancelling: None,
+            priority_purchase_cooldown_ns: 0,
+            last_priority_purchase: UnorderedMap::new(b"y"),
+            min_reserve_ratio_bps: 0,
+            creator_lp_positions: UnorderedMap::new(b"z"),
+            creator_wins: UnorderedMap::new(b"j"),
+            early_vote_bonus_bps: 0,
+            queue_order: QueueOrder::Fifo,
+            min_participation: 0,
+            voting_grace_ns: 0,
+            fee_burn_bps: 0,
+            admins: UnorderedSet::new(b"d"),
+            min_pool_liquidity_for_purchase: 0,
+            withdraw_penalty_bps: 0,
+            pause_flags: PauseFlags::default(),
+            keeper_fund: 0,
         }
     }
 
-    pub fn create_token(&mut self, metadata: TokenMetadata) -> TokenId {
-        let token_id = self.token_counter;
-        let token = Token::new(
-            token_id,
-            env::predecessor_account_id(),
-            "ipfs://".to_string(),
-            metadata,
-        );
+    /// Re-initializes the contract from the pre-versioning state layout.
+    ///
+    /// Old -> new field mapping:
+    /// - `owner_id`, `token_counter`, `token_queue`, `min_stake`: carried
+    ///   over unchanged
+    /// - `current_block`: converted from `OldBlock`, not yet extended, with
+    ///   `total_stakes` reset since its stakes are refunded
+    /// - `votes`, `stakes`: the old nested maps shared the outer prefixes and
+    ///   overwrote their entries, so votes are dropped and each stake that
+    ///   still reads back becomes a pending refund; both maps start empty
+    ///   under the fresh prefixes `b"V"` and `b"S"`
+    /// - `tokens`: every `OldToken` is rewritten as a `Token` under the same
+    ///   prefix; `supply_schedule` defaults to `Fixed(DEFAULT_WINNER_SUPPLY)`
+    /// - `version`: set to `CONTRACT_VERSION`
+    ///
+    /// Every other field starts from the same default as in `new`, with
+    /// collections empty under their usual prefixes. History such as
+    /// `creator_wins` is not backfilled from earlier blocks.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let mut old: OldTokenBlocks = env::state_read()
+            .expect("Failed to read old state");
 
-        self.tokens.insert(&token_id, &token);
-        self.token_counter += 1;
-        
-        if let Some(ref mut block) = self.current_block {
-            if block.is_accepting_tokens(env::block_timestamp()) {
-                block.add_token(token_id);
-            } else {
-                self.token_queue.push(token_id);
+        let old_tokens: Vec<(TokenId, OldToken)> = old.tokens.iter().collect();
+        old.tokens.clear();
+        let mut tokens = UnorderedMap::new(b"t");
+        for (token_id, old_token) in old_tokens {
+            tokens.insert(&token_id, &Token::from(old_token));
+        }
+
+        // The old nested voter and stake maps shared the `b"v"`/`b"s"`
+        // prefixes of the outer maps, so their entries overwrote the outer
+        // maps' slots and per-token amounts can't be recovered. Votes are
+        // dropped, and every stake entry that still reads back as its own
+        // account is owed back as a pending refund. Slots are read raw since
+        // most of them no longer hold the type the map expects.
+        let mut refunds: Vec<(AccountId, Balance)> = Vec::new();
+        let (keys, values) = (old.stakes.keys_as_vector(), old.stakes.values_as_vector());
+        for index in 0..keys.len() {
+            let account_id = keys.get_raw(index)
+                .and_then(|raw| AccountId::try_from_slice(&raw).ok());
+            let stake_info = values.get_raw(index)
+                .and_then(|raw| StakeInfo::try_from_slice(&raw).ok());
+            if let (Some(account_id), Some(stake_info)) = (account_id, stake_info) {
+                if stake_info.account_id == account_id {
+                    refunds.push((account_id, stake_info.total_staked));
+                }
             }
-        } else {
-            self.token_queue.push(token_id);
         }
-        
-        token_id
+        // Clearing works on raw keys, but index entries whose slot was
+        // overwritten are left behind, so the new maps take fresh prefixes
+        old.votes.clear();
+        old.stakes.clear();
+
+        let mut contract = Self {
+            owner_id: old.owner_id.clone(),
+            token_counter: old.token_counter,
+            tokens,
+            current_block: old.current_block.map(|block| Block {
+                total_stakes: 0,
+                ..Block::from(block)
+            }),
+            token_queue: old.token_queue,
+            votes: UnorderedMap::new(b"V"),
+            stakes: UnorderedMap::new(b"S"),
+            min_stake: old.min_stake,
+            version: CONTRACT_VERSION.to_string(),
+            platform_fee: PLATFORM_FEE,
+            pools: UnorderedMap::new(b"p"),
+            blocked_hashes: UnorderedSet::new(b"b"),
+            tie_break: TieBreak::OldestFirst,
+            lp_positions: UnorderedMap::new(b"l"),
+            position_counter: 0,
+            lp_balances: UnorderedMap::new(b"m"),
+            add_liquidity_tolerance_bps: DEFAULT_ADD_LIQUIDITY_TOLERANCE_BPS,
+            stake_token: None,
+            pending_distribution: None,
+            block_cooldown_ns: 0,
+            last_block_end: None,
+            max_tokens_per_block: DEFAULT_MAX_TOKENS_PER_BLOCK,
+            token_balances: UnorderedMap::new(b"w"),
+            min_initial_liquidity: DEFAULT_MIN_INITIAL_LIQUIDITY,
+            voting_reward_pool: 0,
+            winners: Vector::new(b"h"),
+            ft_decimals: DEFAULT_FT_DECIMALS,
+            phases: BlockPhaseConfig::default(),
+            refund_batch_size: DEFAULT_REFUND_BATCH_SIZE,
+            max_tokens_per_account_per_day: 0,
+            creation_log: UnorderedMap::new(b"c"),
+            voting_mode: VotingMode::Linear,
+            pool_seed_bps: DEFAULT_POOL_SEED_BPS,
+            max_token_supply: DEFAULT_MAX_TOKEN_SUPPLY,
+            treasury_id: old.owner_id.clone(),
+            pending_refunds: UnorderedMap::new(b"r"),
+            refund_expiry_ns: DEFAULT_REFUND_EXPIRY_NS,
+            total_pool_native: 0,
+            total_pending_refunds: 0,
+            protocol_fee_bps: 0,
+            trade_history: UnorderedMap::new(b"x"),
+            min_refund_amount: 0,
+            accepted_payment_tokens: UnorderedMap::new(b"a"),
+            refund_winner_stakes: false,
+            standings_blackout_ns: 0,
+            creator_bond: 0,
+            token_bonds: UnorderedMap::new(b"o"),
+            stake_weighted_priority: false,
+            priority_purchases: UnorderedMap::new(b"q"),
+            keeper_reward: 0,
+            require_description: false,
+            require_media: false,
+            creator_pending: UnorderedMap::new(b"k"),
+            total_creator_pending: 0,
+            identity_verifier: None,
+            allow_self_vote: true,
+            result_callback: None,
+            cancelling: None,
+            priority_purchase_cooldown_ns: 0,
+            last_priority_purchase: UnorderedMap::new(b"y"),
+            min_reserve_ratio_bps: 0,
+            creator_lp_positions: UnorderedMap::new(b"z"),
+            creator_wins: UnorderedMap::new(b"j"),
+            early_vote_bonus_bps: 0,
+            queue_order: QueueOrder::Fifo,
+            min_participation: 0,
+            voting_grace_ns: 0,
+            fee_burn_bps: 0,
+            admins: UnorderedSet::new(b"d"),
+            min_pool_liquidity_for_purchase: 0,
+            withdraw_penalty_bps: 0,
+            pause_flags: PauseFlags::default(),
+            keeper_fund: 0,
+        };
+        for (account_id, amount) in refunds {
+            contract.record_pending_refund(&account_id, amount);
+        }
+        contract
     }
 
-    pub fn start_block(&mut self) {
+    // Admin function to choose which end of the queue blocks take tokens from
+    pub fn set_queue_order(&mut self, queue_order: QueueOrder) {
+        self.assert_owner();
+        self.queue_order = queue_order;
+    }
+
+    /// Starts the next block; anyone may call it. Admins may override any
+    /// phase duration for this block only; omitted durations use the
+    /// contract defaults.
+    pub fn start_block(
+        &mut self,
+        accepting: Option<u64>,
+        voting: Option<u64>,
+        public: Option<u64>,
+    ) {
+        let overrides = [accepting, voting, public];
+        if overrides.iter().any(Option::is_some) {
+            self.assert_owner();
+            assert!(
+                overrides.iter().flatten().all(|&duration| duration > 0),
+                "Phase durations must be positive"
+            );
+        }
+        self.internal_start_block(accepting, voting, public);
+    }
+
+    fn internal_start_block(
+        &mut self,
+        accepting: Option<u64>,
+        voting: Option<u64>,
+        public: Option<u64>,
+    ) {
         assert!(self.current_block.is_none(), "Block already in progress");
         assert!(!self.token_queue.is_empty(), "No tokens in queue");
+        assert!(self.is_cooldown_elapsed(), "Block cooldown not elapsed");
         
         let start_time = env::block_timestamp();
         let mut block = Block::new(
             start_time,
-            ACCEPTING_TOKENS_DURATION,
-            VOTING_DURATION,
-            PUBLIC_DURATION,
+            accepting.unwrap_or(self.phases.accepting_tokens_duration),
+            voting.unwrap_or(self.phases.voting_duration),
+            public.unwrap_or(self.phases.public_duration),
             self.min_stake,
             MAX_WINNERS,
         );
 
-        while let Some(token_id) = self.token_queue.pop() {
+        // Tokens beyond the per-block cap stay queued for the next block
+        let token_ids = self.preview_next_block();
+        match self.queue_order {
+            QueueOrder::Fifo => {
+                self.token_queue.drain(..token_ids.len());
+            }
+            QueueOrder::Lifo => {
+                self.token_queue.truncate(self.token_queue.len() - token_ids.len());
+            }
+        }
+        for token_id in token_ids {
             block.add_token(token_id);
         }
 
         self.current_block = Some(block);
     }
 
-    pub fn update_block_phase(&mut self) {
+    pub fn update_block_phase(&mut self) -> PhaseUpdateResult {
+        let previous_phase = self.current_block.as_ref().map(|block| block.phase.clone());
+        // A block about to complete below `min_participation` gets its grace
+        // period of voting first, as it would through `finalize_voting`
+        let completing = self.current_block.as_ref()
+            .map_or(false, |block| env::block_timestamp() >= block.phase_boundaries().2);
+        if completing && self.pending_distribution.is_none() {
+            self.extend_low_participation_voting();
+        }
+
         if let Some(ref mut block) = self.current_block {
-            let previous_phase = block.phase.clone();
+            let previous_phase = previous_phase.unwrap();
             block.update_phase(env::block_timestamp());
+            let phase = block.phase.clone();
     
             // Only update token statuses if the phase has changed
             if block.phase != previous_phase {
+                Events::phase_changed(
+                    &phase_name(&previous_phase),
+                    &phase_name(&block.phase),
+                    block.start_time,
+                );
                 self.update_tokens_status(&block.tokens, &block.phase);
             }
     
-            if matches!(block.phase, BlockPhase::Completed) {
-                self.current_block = None;
-                if !self.token_queue.is_empty() {
-                    self.start_block();
+            // Completion goes through finalization so votes are never
+            // dropped, including when the public phase is skipped. It waits
+            // for a previous block's distribution to finish.
+            let finalized = matches!(phase, BlockPhase::Completed) && self.pending_distribution.is_none();
+            if finalized {
+                self.internal_finalize_voting();
+            }
+            if phase != previous_phase || finalized {
+                self.pay_keeper();
+            }
+
+            if phase == previous_phase {
+                PhaseUpdateResult::Unchanged(phase_name(&phase))
+            } else {
+                PhaseUpdateResult::Transitioned {
+                    from: phase_name(&previous_phase),
+                    to: phase_name(&phase),
                 }
             }
+        } else {
+            PhaseUpdateResult::NoBlock
         }
     }
 
-    #[payable]
-    pub fn vote(&mut self, token_id: TokenId) -> bool {
-        let stake_amount = env::attached_deposit();
-        let voter = env::predecessor_account_id();
+    // Admin function to set the phase durations used for new blocks
+    pub fn set_block_phases(&mut self, phases: BlockPhaseConfig) {
+        self.assert_owner();
+        assert!(
+            phases.accepting_tokens_duration > 0 && phases.voting_duration > 0,
+            "Accepting and voting phases are required"
+        );
+        self.phases = phases;
+    }
 
-        self.assert_active_voting_phase();
-        assert!(stake_amount >= MIN_STAKE_AMOUNT, "Stake too low");
+    // Admin function to cap how many tokens compete in one block
+    pub fn set_max_tokens_per_block(&mut self, max_tokens: u32) {
+        self.assert_owner();
+        assert!(max_tokens > 0, "Cap must be positive");
+        self.max_tokens_per_block = max_tokens;
+    }
 
-        let token = self.tokens.get(&token_id)
-            .expect("Token not found");
-        assert_eq!(token.status, TokenStatus::InVoting, "Token not in voting phase");
+    // Admin function to set the minimum gap between consecutive blocks
+    pub fn set_block_cooldown(&mut self, cooldown_ns: u64) {
+        self.assert_owner();
+        self.block_cooldown_ns = cooldown_ns;
+    }
 
-        let mut vote_info = self.votes.get(&token_id)
-            .unwrap_or_else(|| VoteInfo::new());
-        vote_info.add_vote(&voter, stake_amount);
-        self.votes.insert(&token_id, &vote_info);
+    // Admin function to set the bounty for advancing a block (0 = none)
+    pub fn set_keeper_reward(&mut self, keeper_reward: U128) {
+        self.assert_owner();
+        self.keeper_reward = keeper_reward.0;
+    }
 
-        let mut stake_info = self.stakes.get(&voter)
-            .unwrap_or_else(|| StakeInfo::new(voter.clone()));
-        stake_info.add_stake(token_id, stake_amount);
-        self.stakes.insert(&voter, &stake_info);
+    // Admin function to top up the balance keeper rewards are paid from
+    #[payable]
+    pub fn fund_keeper_rewards(&mut self) -> U128 {
+        self.assert_owner();
+        assert!(env::attached_deposit() > 0, "Deposit required");
+        self.keeper_fund += env::attached_deposit();
+        U128(self.keeper_fund)
+    }
 
-        if let Some(block) = &mut self.current_block {
-            block.total_stakes += stake_amount;
+    // Pays `keeper_reward` to the caller for advancing a block. It comes
+    // out of `keeper_fund`, and is skipped when the fund can't cover it.
+    pub(crate) fn pay_keeper(&mut self) {
+        if self.keeper_reward > 0 && self.keeper_fund >= self.keeper_reward {
+            self.keeper_fund -= self.keeper_reward;
+            Promise::new(env::predecessor_account_id()).transfer(self.keeper_reward);
         }
-
-        true
     }
 
-    pub fn process_voting_results(&mut self) {
-        assert!(self.is_voting_phase_ended(), "Voting phase not ended");
-        
-        // Move the block out of `self.current_block` using `take()`
-        let block = self.current_block.take()
-            .expect("No active block");
-    
-        // Now, you can mutably borrow `self` without conflicts
-        let mut token_votes: Vec<(TokenId, Balance)> = block.tokens.iter()
-            .map(|&token_id| {
-                let votes = self.votes.get(&token_id)
-                    .map(|v| v.total_votes)
-                    .unwrap_or(0);
-                (token_id, votes)
-            })
-            .collect();
-    
-        token_votes.sort_by(|a, b| b.1.cmp(&a.1));
-        let winners: Vec<TokenId> = token_votes.iter()
-            .take(MAX_WINNERS as usize)
-            .map(|(id, _)| *id)
-            .collect();
-    
-        for &token_id in &block.tokens {
-            let mut token = self.tokens.get(&token_id)
-                .expect("Token not found");
-    
-            if winners.contains(&token_id) {
-                token.status = TokenStatus::Winner;
-                token.initialize_supply(1_000_000);
-            } else {
-                token.status = TokenStatus::Lost;
-                self.return_stakes(token_id);
-            }
-    
-            self.tokens.insert(&token_id, &token);
-        }
-    
-        // Optionally, start a new block if there are tokens in the queue
-        if !self.token_queue.is_empty() {
-            self.start_block();
-        } else {
-            self.current_block = None;
+    // Closes the current block and starts the next one if the queue has
+    // tokens and the cooldown allows it
+    fn complete_block(&mut self) {
+        self.current_block = None;
+        self.last_block_end = Some(env::block_timestamp());
+        if !self.token_queue.is_empty() && self.is_cooldown_elapsed() {
+            self.internal_start_block(None, None, None);
         }
     }
 
+    fn is_cooldown_elapsed(&self) -> bool {
+        self.last_block_end
+            .map(|end| env::block_timestamp() >= end + self.block_cooldown_ns)
+            .unwrap_or(true)
+    }
+
     // View methods
+    pub fn get_version(&self) -> String {
+        self.version.clone()
+    }
+
     pub fn get_token(&self, token_id: TokenId) -> Option<TokenView> {
         self.tokens.get(&token_id).map(|token: Token| (&token).into())
     }
     
     pub fn get_tokens_by_creator(&self, creator: AccountId) -> Vec<TokenView> {
-        self.tokens
-            .iter()
-            .filter(|(_, token)| token.creator == creator)
-            .map(|(_, token): (TokenId, Token)| (&token).into())
-            .collect()
+        let mut tokens: Vec<Token> = self.tokens
+            .values()
+            .filter(|token| token.creator == creator)
+            .collect();
+        tokens.sort_by_key(|token| token.id);
+        tokens.iter().map(|token| token.into()).collect()
     }
 
     pub fn get_current_block(&self) -> Option<BlockView> {
         self.current_block.as_ref().map(BlockView::from)
     }
 
+    pub fn get_time_remaining_in_phase(&self) -> Option<u64> {
+        self.current_block.as_ref()
+            .map(|block| block.phase_end_time().saturating_sub(env::block_timestamp()))
+    }
+
+    /// Queued tokens the next `start_block` would take, in the order it
+    /// takes them: oldest or newest first per `queue_order`, up to
+    /// `max_tokens_per_block`
+    pub fn preview_next_block(&self) -> Vec<TokenId> {
+        let limit = self.max_tokens_per_block as usize;
+        match self.queue_order {
+            QueueOrder::Fifo => self.token_queue.iter().take(limit).copied().collect(),
+            QueueOrder::Lifo => self.token_queue.iter().rev().take(limit).copied().collect(),
+        }
+    }
+
+    /// `get_time_remaining_in_phase` in whole seconds, rounded down
+    pub fn get_seconds_until_next_phase(&self) -> Option<u64> {
+        self.get_time_remaining_in_phase()
+            .map(|remaining| remaining / 1_000_000_000)
+    }
+
+    pub fn get_block_summary(&self) -> Option<BlockSummaryView> {
+        let block = self.current_block.as_ref()?;
+        // Standings stay empty through the blackout
+        let mut standings = if self.in_standings_blackout() {
+            Vec::new()
+        } else {
+            self.get_current_standings()
+        };
+        standings.truncate(SUMMARY_STANDINGS_LIMIT);
+
+        Some(BlockSummaryView {
+            block: BlockView::from(block),
+            standings,
+            time_remaining_in_phase: block.phase_end_time().saturating_sub(env::block_timestamp()),
+        })
+    }
+
+    pub fn get_current_block_tokens(&self) -> Vec<TokenView> {
+        self.current_block.as_ref()
+            .map(|block| {
+                block.tokens.iter()
+                    .take(self.max_tokens_per_block as usize)
+                    .filter_map(|token_id| self.tokens.get(token_id))
+                    .map(|token| (&token).into())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub fn get_queued_tokens(&self) -> Vec<TokenId> {
         self.token_queue.clone()
     }
 
     pub fn get_block_info(&self) -> (u64, Balance, u8) {
-        (BLOCK_DURATION, MIN_STAKE_AMOUNT, MAX_WINNERS)
+        let (accepting, voting, public) = self.get_durations();
+        (accepting + voting + public, self.min_stake, MAX_WINNERS)
+    }
+
+    /// `(accepting_end, voting_end, public_end, complete)` timestamps of the
+    /// active block. The block completes as soon as the public phase ends.
+    pub fn get_phase_boundaries(&self) -> Option<(u64, u64, u64, u64)> {
+        self.current_block.as_ref().map(|block| {
+            let (accepting_end, voting_end, public_end) = block.phase_boundaries();
+            (accepting_end, voting_end, public_end, public_end)
+        })
+    }
+
+    /// `(accepting_tokens, voting, public)` durations new blocks are
+    /// started with, in nanoseconds
+    pub fn get_durations(&self) -> (u64, u64, u64) {
+        (
+            self.phases.accepting_tokens_duration,
+            self.phases.voting_duration,
+            self.phases.public_duration,
+        )
+    }
+
+    pub fn get_config(&self) -> ContractConfig {
+        ContractConfig {
+            version: self.version.clone(),
+            min_stake: U128(self.min_stake),
+            platform_fee: U128(self.platform_fee),
+            accepting_tokens_duration: self.phases.accepting_tokens_duration,
+            voting_duration: self.phases.voting_duration,
+            public_duration: self.phases.public_duration,
+            max_winners: MAX_WINNERS,
+            tie_break: self.tie_break.clone(),
+            add_liquidity_tolerance_bps: self.add_liquidity_tolerance_bps,
+            stake_token: self.stake_token.clone(),
+            block_cooldown_ns: self.block_cooldown_ns,
+            max_tokens_per_block: self.max_tokens_per_block,
+            min_initial_liquidity: U128(self.min_initial_liquidity),
+            ft_decimals: self.ft_decimals,
+            refund_batch_size: self.refund_batch_size,
+            max_tokens_per_account_per_day: self.max_tokens_per_account_per_day,
+            voting_mode: self.voting_mode.clone(),
+            pool_seed_bps: self.pool_seed_bps,
+            max_token_supply: U128(self.max_token_supply),
+            treasury_id: self.treasury_id.clone(),
+            refund_expiry_ns: self.refund_expiry_ns,
+            protocol_fee_bps: self.protocol_fee_bps,
+            min_refund_amount: U128(self.min_refund_amount),
+            refund_winner_stakes: self.refund_winner_stakes,
+            standings_blackout_ns: self.standings_blackout_ns,
+            creator_bond: U128(self.creator_bond),
+            stake_weighted_priority: self.stake_weighted_priority,
+            keeper_reward: U128(self.keeper_reward),
+            require_description: self.require_description,
+            require_media: self.require_media,
+            identity_verifier: self.identity_verifier.clone(),
+            allow_self_vote: self.allow_self_vote,
+            result_callback: self.result_callback.clone(),
+            priority_purchase_cooldown_ns: self.priority_purchase_cooldown_ns,
+            min_reserve_ratio_bps: self.min_reserve_ratio_bps,
+            early_vote_bonus_bps: self.early_vote_bonus_bps,
+            queue_order: self.queue_order.clone(),
+            min_participation: U128(self.min_participation),
+            voting_grace_ns: self.voting_grace_ns,
+            fee_burn_bps: self.fee_burn_bps,
+            min_pool_liquidity_for_purchase: U128(self.min_pool_liquidity_for_purchase),
+            withdraw_penalty_bps: self.withdraw_penalty_bps,
+            pause_flags: self.pause_flags,
+        }
     }
 
     pub fn get_votes(&self, token_id: TokenId) -> Option<U128> {
+        let in_block = self.current_block.as_ref()
+            .map(|block| block.tokens.contains(&token_id))
+            .unwrap_or(false);
+        if in_block {
+            self.assert_standings_visible();
+        }
         self.votes.get(&token_id)
             .map(|v| U128(v.total_votes))
     }
@@ -222,35 +743,104 @@ impl TokenBlocks {
             .map(|s| U128(s.total_staked))
     }
 
-    // Helper methods
-    fn return_stakes(&mut self, token_id: TokenId) {
-        if let Some(vote_info) = self.votes.get(&token_id) {
-            for (voter, amount) in vote_info.voters.iter() {
-                Promise::new(voter).transfer(amount);
-            }
-        }
+    /// Everything the account holds, one entry per token balance, stake and
+    /// LP position, then any pending refund. Entries of each kind come in
+    /// token id order. Paginated across all kinds.
+    pub fn export_positions(&self, account_id: AccountId, from_index: u64, limit: u64) -> Vec<PositionEntry> {
+        let entry = |kind, token_id, amount| PositionEntry { kind, token_id, amount: U128(amount) };
+        let balances = self.token_balances.get(&account_id).unwrap_or_default();
+        let lp_balances = self.lp_balances.get(&account_id).unwrap_or_default();
+        let mut stakes: Vec<(TokenId, Balance)> = self.stakes.get(&account_id)
+            .map(|stake_info| stake_info.stakes.to_vec())
+            .unwrap_or_default();
+        stakes.sort_by_key(|(token_id, _)| *token_id);
+
+        balances.into_iter()
+            .map(|(token_id, amount)| entry(PositionKind::TokenBalance, Some(token_id), amount))
+            .chain(stakes.into_iter().map(|(token_id, amount)| entry(PositionKind::Stake, Some(token_id), amount)))
+            .chain(lp_balances.into_iter().map(|(token_id, amount)| entry(PositionKind::Lp, Some(token_id), amount)))
+            .chain(self.pending_refunds.get(&account_id)
+                .map(|refund| entry(PositionKind::PendingRefund, None, refund.amount.0)))
+            .filter(|position| position.amount.0 > 0)
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
     }
 
-    fn assert_active_voting_phase(&self) {
-        assert!(self.current_block.is_some(), "No active block");
-        let block = self.current_block.as_ref().unwrap();
-        assert!(
-            matches!(block.phase, BlockPhase::Voting),
-            "Not in voting phase"
-        );
+    /// Splits the contract's native balance into what pools, pending
+    /// refunds and creators are owed, and what is left over
+    pub fn get_balance_breakdown(&self) -> BalanceBreakdown {
+        let account_balance = env::account_balance();
+        BalanceBreakdown {
+            account_balance: U128(account_balance),
+            pool_native: U128(self.total_pool_native),
+            pending_refunds: U128(self.total_pending_refunds),
+            creator_pending: U128(self.total_creator_pending),
+            keeper_fund: U128(self.keeper_fund),
+            free: U128(account_balance
+                .saturating_sub(self.total_pool_native)
+                .saturating_sub(self.total_pending_refunds)
+                .saturating_sub(self.total_creator_pending)
+                .saturating_sub(self.keeper_fund)),
+        }
     }
 
-    fn is_voting_phase_ended(&self) -> bool {
-        if let Some(block) = &self.current_block {
-            env::block_timestamp() >= block.voting_end_time
-        } else {
-            false
+    /// Checks internal invariants and describes every violation found;
+    /// an empty list means the state is consistent
+    pub fn audit(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        for (token_id, token) in self.tokens.iter() {
+            if token.circulating_supply + token.pool_reserve > token.total_supply {
+                violations.push(format!(
+                    "Token {}: circulating {} + reserve {} exceeds total supply {}",
+                    token_id, token.circulating_supply, token.pool_reserve, token.total_supply
+                ));
+            }
+        }
+
+        let native_reserves: Balance = self.pools.values()
+            .map(|pool| pool.native_reserve)
+            .sum();
+        if native_reserves > env::account_balance() {
+            violations.push(format!(
+                "Pool native reserves {} exceed account balance {}",
+                native_reserves,
+                env::account_balance()
+            ));
+        }
+        if native_reserves != self.total_pool_native {
+            violations.push(format!(
+                "Pool native total {} does not match summed reserves {}",
+                self.total_pool_native, native_reserves
+            ));
         }
+
+        // Summed per voter, as `total_votes` is voting power in quadratic mode
+        if let Some(ref block) = self.current_block {
+            let staked: Balance = block.tokens.iter()
+                .filter_map(|token_id| self.votes.get(token_id))
+                .map(|vote_info| vote_info.voters.values().sum::<Balance>())
+                .sum();
+            if staked != block.total_stakes {
+                violations.push(format!(
+                    "Block total stakes {} does not match summed votes {}",
+                    block.total_stakes, staked
+                ));
+            }
+        }
+
+        violations
     }
 
+    // Helper methods
     fn update_tokens_status(&mut self, token_ids: &[TokenId], phase: &BlockPhase) {
         for &token_id in token_ids {
             if let Some(mut token) = self.tokens.get(&token_id) {
+                // Moderated tokens keep their status for good
+                if token.status == TokenStatus::Removed {
+                    continue;
+                }
                 token.status = match phase {
                     BlockPhase::AcceptingTokens => TokenStatus::Pending,
                     BlockPhase::Voting => TokenStatus::InVoting,
@@ -263,12 +853,48 @@ impl TokenBlocks {
     }
 }
 
+// Adds `amount` to an account's per-token balance in `balances`
+pub(crate) fn credit_balance(
+    balances: &mut UnorderedMap<AccountId, BTreeMap<TokenId, Balance>>,
+    account_id: &AccountId,
+    token_id: TokenId,
+    amount: Balance,
+) {
+    let mut account_balances = balances.get(account_id).unwrap_or_default();
+    *account_balances.entry(token_id).or_insert(0) += amount;
+    balances.insert(account_id, &account_balances);
+}
+
+// Removes `amount` from an account's per-token balance, dropping zero entries
+pub(crate) fn debit_balance(
+    balances: &mut UnorderedMap<AccountId, BTreeMap<TokenId, Balance>>,
+    account_id: &AccountId,
+    token_id: TokenId,
+    amount: Balance,
+) {
+    let mut account_balances = balances.get(account_id)
+        .expect("No balance");
+    let balance = account_balances.get_mut(&token_id)
+        .expect("No balance");
+    assert!(*balance >= amount, "Insufficient balance");
+    *balance -= amount;
+    if *balance == 0 {
+        account_balances.remove(&token_id);
+    }
+    if account_balances.is_empty() {
+        balances.remove(account_id);
+    } else {
+        balances.insert(account_id, &account_balances);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
     use near_sdk::testing_env;
     use near_sdk::MockedBlockchain;
+    use near_sdk::PromiseOrValue;
     use near_sdk::json_types::ValidAccountId;
 
     fn get_context() -> VMContextBuilder {
@@ -279,6 +905,78 @@ mod tests {
         builder
     }
 
+    // Generous storage allowance on top of the platform fee; the excess is refunded
+    const STORAGE_DEPOSIT: Balance = 100_000_000_000_000_000_000_000;
+    const CREATION_DEPOSIT: Balance = PLATFORM_FEE + STORAGE_DEPOSIT;
+
+    fn create_test_metadata() -> TokenMetadata {
+        TokenMetadata {
+            title: "Test Token".to_string(),
+            description: Some("Test Description".to_string()),
+            media: None,
+            media_hash: None,
+            copies: Some(1000),
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            symbol: None,
+        }
+    }
+
+    fn set_caller(context: &mut VMContextBuilder, account_id: &str, deposit: Balance) {
+        context
+            .predecessor_account_id(ValidAccountId::try_from(account_id.to_string()).unwrap())
+            .attached_deposit(deposit);
+        testing_env!(context.build());
+    }
+
+    // Every transfer receipt created so far, as `(receiver, amount)`
+    fn transfers() -> Vec<(String, Balance)> {
+        get_created_receipts()
+            .into_iter()
+            .filter_map(|receipt| match receipt.actions.first() {
+                Some(near_sdk::mock::VmAction::Transfer { deposit }) => Some((receipt.receiver_id.to_string(), *deposit)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Runs `configure` as the owner on a fresh contract, then creates
+    // `count` tokens and starts a block with them, still accepting tokens
+    fn setup_block(
+        context: &mut VMContextBuilder,
+        count: usize,
+        configure: impl FnOnce(&mut TokenBlocks),
+    ) -> (TokenBlocks, Vec<TokenId>) {
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        configure(&mut contract);
+
+        set_caller(context, "creator.near", CREATION_DEPOSIT);
+        let token_ids = (0..count)
+            .map(|_| contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None))
+            .collect();
+
+        set_caller(context, "owner.near", 0);
+        contract.start_block(None, None, None);
+        (contract, token_ids)
+    }
+
+    // Moves a block from `setup_block` into the Voting phase
+    fn open_voting(context: &mut VMContextBuilder, contract: &mut TokenBlocks) {
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + 1);
+        testing_env!(context.build());
+        contract.update_block_phase();
+    }
+
+    // Creates `count` tokens, starts a block and moves it into the Voting phase
+    fn setup_voting_block(context: &mut VMContextBuilder, count: usize) -> (TokenBlocks, Vec<TokenId>) {
+        let (mut contract, token_ids) = setup_block(context, count, |_| {});
+        open_voting(context, &mut contract);
+        (contract, token_ids)
+    }
+
     #[test]
     fn test_create_token() {
         let context = get_context();
@@ -296,6 +994,7 @@ mod tests {
             expires_at: None,
             starts_at: None,
             extra: None,
+            symbol: None,
         };
 
         let token_id = contract.create_token(metadata.clone());
@@ -322,12 +1021,15 @@ mod tests {
             expires_at: None,
             starts_at: None,
             extra: None,
+            symbol: None,
         };
     
-        let token_id = contract.create_token(metadata);
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token("ipfs://content".to_string(), metadata, None, None);
+        set_caller(&mut context, "owner.near", 0);
         assert!(contract.get_queued_tokens().contains(&token_id));
     
-        contract.start_block();
+        contract.start_block(None, None, None);
         let block = contract.get_current_block().unwrap();
         assert_eq!(block.tokens.len(), 1);
     
@@ -362,10 +1064,13 @@ mod tests {
             expires_at: None,
             starts_at: None,
             extra: None,
+            symbol: None,
         };
 
-        let token_id = contract.create_token(metadata);
-        contract.start_block();
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token("ipfs://content".to_string(), metadata, None, None);
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
 
         // Move time forward past accepting tokens phase
         let voting_start_time = ACCEPTING_TOKENS_DURATION + 1;
@@ -390,4 +1095,2671 @@ mod tests {
         let votes = contract.get_votes(token_id).unwrap();
         assert_eq!(votes.0, MIN_STAKE_AMOUNT);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_migrate_from_old_state() {
+        let context = get_context();
+        testing_env!(context.build());
+
+        let mut tokens = UnorderedMap::new(b"t");
+        let metadata = OldTokenMetadata {
+            title: "Old Token".to_string(),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: Some(1000),
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+        };
+        let token = OldToken {
+            id: 0,
+            creator: AccountId::try_from("creator.near".to_string()).unwrap(),
+            content_hash: "ipfs://old".to_string(),
+            created_at: 0,
+            total_supply: 0,
+            circulating_supply: 0,
+            pool_reserve: 0,
+            status: TokenStatus::Queued,
+            metadata,
+        };
+        tokens.insert(&0, &token);
+
+        let old = OldTokenBlocks {
+            owner_id: AccountId::try_from("owner.near".to_string()).unwrap(),
+            token_counter: 1,
+            tokens,
+            current_block: None,
+            token_queue: vec![0],
+            votes: UnorderedMap::new(b"v"),
+            stakes: UnorderedMap::new(b"s"),
+            min_stake: MIN_STAKE_AMOUNT,
+        };
+        env::state_write(&old);
+
+        let contract = TokenBlocks::migrate();
+        assert_eq!(contract.get_version(), CONTRACT_VERSION);
+        assert_eq!(contract.token_counter, 1);
+        assert_eq!(contract.platform_fee, PLATFORM_FEE);
+        assert_eq!(contract.get_queued_tokens(), vec![0]);
+        assert_eq!(contract.get_token(0).unwrap().metadata.title, "Old Token");
+        assert_eq!(
+            contract.tokens.get(&0).unwrap().supply_schedule,
+            SupplySchedule::Fixed(DEFAULT_WINNER_SUPPLY)
+        );
+    }
+
+    #[test]
+    fn test_migrate_old_block_and_votes() {
+        let context = get_context();
+        testing_env!(context.build());
+
+        // Vote the way the pre-versioning contract did, with each nested
+        // map created under its outer map's prefix
+        let mut votes: UnorderedMap<TokenId, OldVoteInfo> = UnorderedMap::new(b"v");
+        let mut stakes: UnorderedMap<AccountId, StakeInfo> = UnorderedMap::new(b"s");
+        for &(voter, token_id) in &[("alice.near", 0), ("bob.near", 1)] {
+            let voter = AccountId::try_from(voter.to_string()).unwrap();
+            let mut vote_info = votes.get(&token_id).unwrap_or_else(|| OldVoteInfo {
+                total_votes: 0,
+                voters: UnorderedMap::new(b"v".to_vec()),
+            });
+            vote_info.voters.insert(&voter, &MIN_STAKE_AMOUNT);
+            vote_info.total_votes += MIN_STAKE_AMOUNT;
+            votes.insert(&token_id, &vote_info);
+
+            let mut stake_info = stakes.get(&voter).unwrap_or_else(|| StakeInfo {
+                account_id: voter.clone(),
+                stakes: UnorderedMap::new(b"s".to_vec()),
+                total_staked: 0,
+            });
+            stake_info.add_stake(token_id, MIN_STAKE_AMOUNT);
+            stakes.insert(&voter, &stake_info);
+        }
+
+        let old = OldTokenBlocks {
+            owner_id: AccountId::try_from("owner.near".to_string()).unwrap(),
+            token_counter: 2,
+            tokens: UnorderedMap::new(b"t"),
+            current_block: Some(OldBlock {
+                start_time: 0,
+                accepting_tokens_duration: ACCEPTING_TOKENS_DURATION,
+                voting_duration: VOTING_DURATION,
+                public_duration: PUBLIC_DURATION,
+                min_stake: MIN_STAKE_AMOUNT,
+                max_winners: 1,
+                tokens: vec![0, 1],
+                total_stakes: MIN_STAKE_AMOUNT * 2,
+                phase: BlockPhase::Voting,
+                voting_end_time: ACCEPTING_TOKENS_DURATION + VOTING_DURATION,
+            }),
+            token_queue: vec![],
+            votes,
+            stakes,
+            min_stake: MIN_STAKE_AMOUNT,
+        };
+        env::state_write(&old);
+
+        let mut contract = TokenBlocks::migrate();
+        let block = contract.current_block.as_ref().unwrap();
+        assert_eq!(block.tokens, vec![0, 1]);
+        assert!(!block.voting_extended);
+        assert_eq!(block.total_stakes, 0);
+        assert!(contract.votes.get(&0).is_none());
+        assert!(contract.votes.get(&1).is_none());
+
+        // Bob's nested stake landed in alice's slot, so only bob's entry
+        // still reads back
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        let bob = AccountId::try_from("bob.near".to_string()).unwrap();
+        assert_eq!(contract.get_pending_refund(alice.clone()), None);
+        assert_eq!(contract.get_pending_refund(bob).unwrap().amount, U128(MIN_STAKE_AMOUNT));
+        assert_eq!(contract.total_pending_refunds, MIN_STAKE_AMOUNT);
+
+        // Leftover old index entries don't shadow the migrated maps
+        let mut stake_info = StakeInfo::new(alice.clone());
+        stake_info.add_stake(0, MIN_STAKE_AMOUNT);
+        contract.stakes.insert(&alice, &stake_info);
+        assert_eq!(contract.stakes.get(&alice).unwrap().total_staked, MIN_STAKE_AMOUNT);
+    }
+
+    #[test]
+    fn test_winning_cutoff() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 5);
+        contract.current_block.as_mut().unwrap().max_winners = 3;
+
+        for (i, &token_id) in token_ids.iter().enumerate() {
+            set_caller(&mut context, "voter.near", MIN_STAKE_AMOUNT * (i as u128 + 1));
+            contract.vote(token_id);
+        }
+
+        // Votes are 1..=5 NEAR, so the 3rd place holds 3 NEAR
+        assert_eq!(contract.get_winning_cutoff(), Some(U128(MIN_STAKE_AMOUNT * 3)));
+
+        contract.current_block.as_mut().unwrap().max_winners = 6;
+        assert_eq!(contract.get_winning_cutoff(), None);
+    }
+
+    #[test]
+    fn test_create_tokens_batch() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "creator.near", PLATFORM_FEE * 3 + STORAGE_DEPOSIT);
+        let items = (0..3)
+            .map(|i| (format!("ipfs://content-{}", i), create_test_metadata()))
+            .collect();
+        let token_ids = contract.create_tokens(items);
+
+        assert_eq!(token_ids, vec![0, 1, 2]);
+        assert_eq!(contract.get_queued_tokens(), vec![0, 1, 2]);
+        // Only the single excess refund is scheduled
+        assert_eq!(get_created_receipts().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Content is blocked")]
+    fn test_create_token_with_blocked_hash() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.block_hash("ipfs://abusive".to_string());
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://abusive".to_string(), create_test_metadata(), None, None);
+    }
+
+    #[test]
+    fn test_remove_existing_token() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.remove_token(token_id);
+        assert_eq!(contract.get_token(token_id).unwrap().status, TokenStatus::Removed);
+        assert!(contract.get_queued_tokens().is_empty());
+    }
+
+    #[test]
+    fn test_remove_token_frees_block_slot() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_block(&mut context, 1, |_| {});
+        assert_eq!(contract.current_block.as_ref().unwrap().tokens, token_ids);
+
+        contract.remove_token(token_ids[0]);
+        assert!(contract.current_block.as_ref().unwrap().tokens.is_empty());
+    }
+
+    #[test]
+    fn test_get_config_reflects_setters() {
+        let context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        contract.update_platform_fee(U128(42));
+        contract.update_min_stake(U128(7));
+
+        let config = contract.get_config();
+        assert_eq!(config.platform_fee, U128(42));
+        assert_eq!(config.min_stake, U128(7));
+        assert_eq!(config.max_winners, MAX_WINNERS);
+        assert_eq!(config.voting_duration, VOTING_DURATION);
+        assert_eq!(config.version, CONTRACT_VERSION);
+    }
+
+    #[test]
+    fn test_random_tie_break_is_reproducible() {
+        let mut context = get_context();
+        let (mut contract, _) = setup_voting_block(&mut context, 5);
+        contract.current_block.as_mut().unwrap().max_winners = 3;
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_tie_break(TieBreak::Random);
+
+        context.random_seed([7; 32]);
+        testing_env!(context.build());
+
+        // All five tokens are tied at zero votes
+        let block = contract.current_block.as_ref().unwrap();
+        let winners = contract.compute_winners(block);
+        assert_eq!(winners.len(), 3);
+        assert_eq!(contract.compute_winners(block), winners);
+    }
+
+    #[test]
+    fn test_block_summary_matches_views() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let contract = TokenBlocks::new("owner.near".to_string());
+        assert!(contract.get_block_summary().is_none());
+
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 3);
+        set_caller(&mut context, "voter.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+
+        let summary = contract.get_block_summary().unwrap();
+        let block = contract.get_current_block().unwrap();
+        assert_eq!(summary.block.phase, block.phase);
+        assert_eq!(summary.block.tokens, block.tokens);
+        assert_eq!(summary.standings, contract.get_current_standings());
+        assert_eq!(summary.standings[0], (token_ids[1], U128(MIN_STAKE_AMOUNT)));
+        assert_eq!(Some(summary.time_remaining_in_phase), contract.get_time_remaining_in_phase());
+    }
+
+    #[test]
+    fn test_audit_reports_corrupted_supply() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        assert!(contract.audit().is_empty());
+
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.circulating_supply = 1;
+        contract.tokens.insert(&token_id, &token);
+
+        let violations = contract.audit();
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].starts_with("Token 0"));
+    }
+
+    #[test]
+    fn test_next_token_id_predicts_creation() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+
+        let predicted = contract.get_next_token_id();
+        let first = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        assert_eq!(first, predicted);
+
+        // Moving the first token into a block doesn't free its id
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let predicted = contract.get_next_token_id();
+        let second = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        assert_eq!(second, predicted);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_supply_info_ratio() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+
+        // Not yet minted
+        assert_eq!(contract.get_supply_info(token_id).circulating_ratio, U128(0));
+
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.initialize_supply(0, Balance::MAX);
+        token.circulating_supply = DEFAULT_WINNER_SUPPLY / 4;
+        contract.tokens.insert(&token_id, &token);
+
+        let info = contract.get_supply_info(token_id);
+        assert_eq!(info.total_supply, U128(DEFAULT_WINNER_SUPPLY));
+        assert_eq!(info.pool_reserve, U128(DEFAULT_WINNER_SUPPLY / 5));
+        assert_eq!(info.circulating_ratio, U128(Math::PRECISION / 4));
+    }
+
+    #[test]
+    fn test_start_block_with_custom_durations() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(Some(10), Some(20), None);
+
+        context.block_timestamp(10);
+        testing_env!(context.build());
+        contract.update_block_phase();
+        assert_eq!(contract.get_current_block().unwrap().phase, "Voting");
+
+        context.block_timestamp(30);
+        testing_env!(context.build());
+        contract.update_block_phase();
+        assert_eq!(contract.get_current_block().unwrap().phase, "Public");
+
+        // Public falls back to the default duration
+        context.block_timestamp(30 + PUBLIC_DURATION - 1);
+        testing_env!(context.build());
+        contract.update_block_phase();
+        assert_eq!(contract.get_current_block().unwrap().phase, "Public");
+    }
+
+    #[test]
+    #[should_panic(expected = "Only contract owner can call this method")]
+    fn test_duration_overrides_require_owner() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        contract.start_block(Some(10), None, None);
+    }
+
+    #[test]
+    fn test_refundable_for_projected_loser() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 2);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        let bob = AccountId::try_from("bob.near".to_string()).unwrap();
+        assert_eq!(contract.get_refundable(alice), U128(0));
+        assert_eq!(contract.get_refundable(bob), U128(MIN_STAKE_AMOUNT));
+    }
+
+    #[test]
+    fn test_ft_stake_vote_and_refund() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+        let gov = AccountId::try_from("gov.near".to_string()).unwrap();
+        contract.stake_token = Some(gov.clone());
+
+        set_caller(&mut context, "gov.near", 0);
+        contract.ft_on_transfer(
+            AccountId::try_from("alice.near".to_string()).unwrap(),
+            U128(MIN_STAKE_AMOUNT * 2),
+            token_ids[0].to_string(),
+        );
+        contract.ft_on_transfer(
+            AccountId::try_from("bob.near".to_string()).unwrap(),
+            U128(MIN_STAKE_AMOUNT),
+            token_ids[1].to_string(),
+        );
+        assert_eq!(contract.get_votes(token_ids[0]), Some(U128(MIN_STAKE_AMOUNT * 2)));
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        // Bob's losing stake goes back through the stake token contract
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, gov);
+    }
+
+    #[test]
+    fn test_finalize_then_distribute_in_batches() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 4);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+
+        // Token 0 wins; tokens 1-3 lose with 4 voters each
+        set_caller(&mut context, "whale.near", MIN_STAKE_AMOUNT * 10);
+        contract.vote(token_ids[0]);
+        for &token_id in &token_ids[1..] {
+            for voter in 0..4 {
+                set_caller(&mut context, &format!("voter{}.near", voter), MIN_STAKE_AMOUNT);
+                contract.vote(token_id);
+            }
+        }
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.finalize_voting();
+        assert_eq!(contract.get_token(token_ids[0]).unwrap().status, TokenStatus::Winner);
+        assert_eq!(contract.get_token(token_ids[1]).unwrap().status, TokenStatus::Lost);
+        assert_eq!(contract.get_token(token_ids[0]).unwrap().total_supply, U128(0));
+
+        // 1 winner + 12 refunds in batches of 5
+        let mut calls = 0;
+        while !contract.distribute(5) {
+            calls += 1;
+        }
+        calls += 1;
+        assert_eq!(calls, 3);
+        // 12 refunds; the creator's share of the winner's stake is held
+        assert_eq!(get_created_receipts().len(), 12);
+        assert_eq!(contract.get_token(token_ids[0]).unwrap().total_supply, U128(DEFAULT_WINNER_SUPPLY));
+        assert!(contract.pending_distribution.is_none());
+    }
+
+    #[test]
+    fn test_creation_cost_covers_create_token() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        let cost = contract.get_creation_cost(create_test_metadata());
+        assert!(cost.0 > PLATFORM_FEE);
+
+        set_caller(&mut context, "creator.near", cost.0);
+        let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        assert!(contract.get_token(token_id).is_some());
+    }
+
+    #[test]
+    fn test_creation_cost_covers_log_and_bond() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_creation_rate_limit(5);
+        contract.set_creator_bond(U128(1_000));
+
+        let cost = contract.get_creation_cost(create_test_metadata());
+        let creator = format!("{}.near", "a".repeat(59));
+        set_caller(&mut context, &creator, cost.0);
+        let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        assert!(contract.get_token(token_id).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient deposit for token creation")]
+    fn test_create_token_charges_storage() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "creator.near", PLATFORM_FEE);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+    }
+
+    #[test]
+    fn test_block_cooldown() {
+        let mut context = get_context();
+        let (mut contract, _) = setup_voting_block(&mut context, 1);
+        contract.set_block_cooldown(1_000);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://next".to_string(), create_test_metadata(), None, None);
+
+        // Completing the block doesn't auto-start the next one during cooldown
+        let end = ACCEPTING_TOKENS_DURATION + VOTING_DURATION + PUBLIC_DURATION;
+        context.block_timestamp(end);
+        set_caller(&mut context, "owner.near", 0);
+        contract.update_block_phase();
+        assert!(contract.get_current_block().is_none());
+
+        context.block_timestamp(end + 1_000);
+        testing_env!(context.build());
+        contract.start_block(None, None, None);
+        assert!(contract.get_current_block().is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Block cooldown not elapsed")]
+    fn test_start_block_within_cooldown() {
+        let mut context = get_context();
+        let (mut contract, _) = setup_voting_block(&mut context, 1);
+        contract.set_block_cooldown(1_000);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://next".to_string(), create_test_metadata(), None, None);
+
+        let end = ACCEPTING_TOKENS_DURATION + VOTING_DURATION + PUBLIC_DURATION;
+        context.block_timestamp(end);
+        set_caller(&mut context, "owner.near", 0);
+        contract.update_block_phase();
+
+        context.block_timestamp(end + 999);
+        testing_env!(context.build());
+        contract.start_block(None, None, None);
+    }
+
+    #[test]
+    fn test_current_block_tokens() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let contract = TokenBlocks::new("owner.near".to_string());
+        assert!(contract.get_current_block_tokens().is_empty());
+
+        let (contract, mut token_ids) = setup_voting_block(&mut context, 3);
+        let mut view_ids: Vec<TokenId> = contract.get_current_block_tokens()
+            .iter()
+            .map(|view| view.id)
+            .collect();
+        view_ids.sort();
+        token_ids.sort();
+        assert_eq!(view_ids, token_ids);
+        assert!(contract.get_current_block_tokens()
+            .iter()
+            .all(|view| view.status == TokenStatus::InVoting));
+    }
+
+    #[test]
+    fn test_start_block_respects_token_cap() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_max_tokens_per_block(2);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        for _ in 0..3 {
+            contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        }
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+        assert_eq!(contract.get_current_block().unwrap().tokens.len(), 2);
+        assert_eq!(contract.get_queued_tokens().len(), 1);
+    }
+
+    #[test]
+    fn test_accepting_block_takes_tokens_up_to_cap() {
+        let mut context = get_context();
+        let (mut contract, mut token_ids) = setup_block(&mut context, 1, |c| c.set_max_tokens_per_block(2));
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let joined = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        let queued = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+
+        token_ids.push(joined);
+        assert_eq!(contract.current_block.as_ref().unwrap().tokens, token_ids);
+        assert_eq!(contract.get_queued_tokens(), vec![queued]);
+    }
+
+    #[test]
+    fn test_winner_gets_pool() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 2);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        let pool = contract.get_pool_info(token_ids[0]);
+        assert_eq!(pool.token_reserve, contract.get_token(token_ids[0]).unwrap().pool_reserve);
+        assert_eq!(pool.native_reserve, U128(MIN_STAKE_AMOUNT));
+        assert!(contract.pools.get(&token_ids[1]).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool already exists")]
+    fn test_create_pool_twice() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.create_pool(token_ids[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Winner not settled yet")]
+    fn test_create_pool_before_settlement() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        // Finalized but not yet distributed: a winner without supply
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.finalize_voting();
+        assert_eq!(contract.tokens.get(&token_ids[0]).unwrap().status, TokenStatus::Winner);
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.create_pool(token_ids[0]);
+    }
+
+    #[test]
+    fn test_rewards_split_by_stake() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+
+        let reward = 900;
+        set_caller(&mut context, "owner.near", reward);
+        contract.fund_rewards();
+        assert_eq!(contract.get_reward_pool(), U128(reward));
+
+        // Token 0 wins with a 2:1 stake split; token 1 loses
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 2);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "carol.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.finalize_voting();
+        assert_eq!(contract.get_reward_pool(), U128(0));
+        assert!(contract.distribute(u32::MAX));
+
+        assert_eq!(transfers(), vec![
+            ("alice.near".to_string(), reward * 2 / 3),
+            ("bob.near".to_string(), reward / 3),
+            ("carol.near".to_string(), MIN_STAKE_AMOUNT),
+        ]);
+        let creator = AccountId::try_from("creator.near".to_string()).unwrap();
+        assert_eq!(contract.get_creator_pending(creator), U128(MIN_STAKE_AMOUNT * 3 / 2));
+    }
+
+    #[test]
+    fn test_update_block_phase_results() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        assert_eq!(contract.update_block_phase(), PhaseUpdateResult::NoBlock);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+
+        assert_eq!(
+            contract.update_block_phase(),
+            PhaseUpdateResult::Unchanged("AcceptingTokens".to_string())
+        );
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + 1);
+        testing_env!(context.build());
+        assert_eq!(
+            contract.update_block_phase(),
+            PhaseUpdateResult::Transitioned {
+                from: "AcceptingTokens".to_string(),
+                to: "Voting".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_all_winners_across_blocks() {
+        let mut context = get_context();
+        let (mut contract, first) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(first[0]);
+
+        let first_end = ACCEPTING_TOKENS_DURATION + VOTING_DURATION;
+        context.block_timestamp(first_end);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let second = contract.create_token("ipfs://second".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+
+        context.block_timestamp(first_end + ACCEPTING_TOKENS_DURATION + 1);
+        testing_env!(context.build());
+        contract.update_block_phase();
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(second);
+
+        context.block_timestamp(first_end + ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        let ids: Vec<TokenId> = contract.get_all_winners(0, 10).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![first[0], second]);
+        assert_eq!(contract.get_all_winners(1, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_ft_metadata_of() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let mut metadata = create_test_metadata();
+        metadata.symbol = Some("TBLK".to_string());
+        let named = contract.create_token("ipfs://named".to_string(), metadata, None, None);
+        let derived = contract.create_token("ipfs://derived".to_string(), create_test_metadata(), None, None);
+
+        let ft_metadata = contract.ft_metadata_of(named);
+        assert_eq!(ft_metadata.symbol, "TBLK");
+        assert_eq!(ft_metadata.name, create_test_metadata().title);
+        assert_eq!(ft_metadata.decimals, DEFAULT_FT_DECIMALS);
+        assert_eq!(ft_metadata.spec, "ft-1.0.0");
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_ft_decimals(18);
+        let ft_metadata = contract.ft_metadata_of(derived);
+        assert_eq!(ft_metadata.symbol, "TESTTOKE");
+        assert_eq!(ft_metadata.decimals, 18);
+    }
+
+    #[test]
+    fn test_flagged_spam_cannot_win() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 5);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.flag_spam(token_ids[0]);
+        // The platform fee goes to the treasury, the owner by default
+        assert_eq!(transfers(), vec![
+            ("alice.near".to_string(), MIN_STAKE_AMOUNT * 5),
+            ("owner.near".to_string(), PLATFORM_FEE),
+        ]);
+        assert_eq!(contract.get_votes(token_ids[0]), None);
+        assert_eq!(contract.get_current_standings(), vec![(token_ids[1], U128(MIN_STAKE_AMOUNT))]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+        assert_eq!(contract.get_token(token_ids[0]).unwrap().status, TokenStatus::Removed);
+        assert_eq!(contract.get_token(token_ids[1]).unwrap().status, TokenStatus::Winner);
+    }
+
+    #[test]
+    fn test_flag_unsettled_winner() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        set_caller(&mut context, "owner.near", 900);
+        contract.fund_rewards();
+        contract.update_platform_fee(U128(PLATFORM_FEE * 2));
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 2);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.finalize_voting();
+
+        // Alice gets her stake and her share of the reward; the treasury
+        // gets the fee the creator paid, not the current one
+        set_caller(&mut context, "owner.near", 0);
+        contract.flag_spam(token_ids[0]);
+        assert_eq!(transfers(), vec![
+            ("alice.near".to_string(), 600),
+            ("alice.near".to_string(), MIN_STAKE_AMOUNT * 2),
+            ("owner.near".to_string(), PLATFORM_FEE),
+        ]);
+        let winners: Vec<TokenId> = contract.get_all_winners(0, 10).iter().map(|view| view.id).collect();
+        assert_eq!(winners, vec![token_ids[1]]);
+        let wins: Vec<TokenId> = contract.get_creator_wins("creator.near".to_string())
+            .iter()
+            .map(|view| view.id)
+            .collect();
+        assert_eq!(wins, vec![token_ids[1]]);
+
+        // Bob's share is unchanged, so the whole reward is paid out
+        assert!(contract.distribute(u32::MAX));
+        assert!(transfers().contains(&("bob.near".to_string(), 300)));
+        assert!(contract.pools.get(&token_ids[0]).is_none());
+    }
+
+    #[test]
+    fn test_flag_spam_forfeits_bond() {
+        let bond = 5 * MIN_STAKE_AMOUNT;
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_creator_bond(U128(bond));
+        contract.set_treasury(AccountId::try_from("treasury.near".to_string()).unwrap());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT + bond);
+        let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.flag_spam(token_id);
+        assert!(contract.token_bonds.is_empty());
+        assert!(transfers().contains(&("treasury.near".to_string(), bond)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token cannot be flagged")]
+    fn test_settled_winner_cannot_be_flagged() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.flag_spam(token_ids[0]);
+    }
+
+    #[test]
+    fn test_skip_public_phase() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_block(&mut context, 1, |c| {
+            c.set_block_phases(BlockPhaseConfig {
+                public_duration: 0,
+                ..BlockPhaseConfig::default()
+            })
+        });
+        open_voting(&mut context, &mut contract);
+        let token_id = token_ids[0];
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_id);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        assert_eq!(
+            contract.update_block_phase(),
+            PhaseUpdateResult::Transitioned {
+                from: "Voting".to_string(),
+                to: "Completed".to_string(),
+            }
+        );
+        assert!(contract.get_current_block().is_none());
+        assert_eq!(contract.get_token(token_id).unwrap().status, TokenStatus::Winner);
+        assert!(contract.pending_distribution.is_some());
+    }
+
+    #[test]
+    fn test_process_refunds_in_batches() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 3);
+        contract.current_block.as_mut().unwrap().max_winners = 0;
+
+        for &token_id in &token_ids {
+            for voter in 0..4 {
+                set_caller(&mut context, &format!("voter{}.near", voter), MIN_STAKE_AMOUNT);
+                contract.vote(token_id);
+            }
+        }
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_refund_batch_size(5);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.finalize_voting();
+        assert_eq!(contract.get_pending_refund_count(), 12);
+
+        assert_eq!(contract.process_refunds(), 7);
+        assert_eq!(contract.process_refunds(), 2);
+        assert_eq!(contract.process_refunds(), 0);
+        assert!(contract.pending_distribution.is_none());
+    }
+
+    #[test]
+    fn test_phase_changed_events() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+
+        let mut events = Vec::new();
+        for timestamp in [
+            ACCEPTING_TOKENS_DURATION,
+            ACCEPTING_TOKENS_DURATION + VOTING_DURATION,
+            ACCEPTING_TOKENS_DURATION + VOTING_DURATION + PUBLIC_DURATION,
+        ] {
+            context.block_timestamp(timestamp);
+            testing_env!(context.build());
+            contract.update_block_phase();
+            events.extend(get_logs());
+        }
+
+        let expected: Vec<String> = [
+            ("AcceptingTokens", "Voting"),
+            ("Voting", "Public"),
+            ("Public", "Completed"),
+        ]
+            .iter()
+            .map(|(from, to)| format!(
+                r#"EVENT_JSON:{{"data":[{{"block_start_time":"0","from":"{}","to":"{}"}}],"event":"phase_changed","standard":"tokenblocks","version":"1.0.0"}}"#,
+                from, to
+            ))
+            .collect();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn test_creation_rate_limit_resets_next_day() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_creation_rate_limit(2);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://one".to_string(), create_test_metadata(), None, None);
+        contract.create_token("ipfs://two".to_string(), create_test_metadata(), None, None);
+
+        context.block_timestamp(Time::ONE_DAY);
+        testing_env!(context.build());
+        contract.create_token("ipfs://three".to_string(), create_test_metadata(), None, None);
+        let creator = AccountId::try_from("creator.near".to_string()).unwrap();
+        assert_eq!(contract.creation_log.get(&creator).unwrap().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Daily token creation limit reached")]
+    fn test_creation_rate_limit_exceeded() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_creation_rate_limit(2);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://one".to_string(), create_test_metadata(), None, None);
+        contract.create_token("ipfs://two".to_string(), create_test_metadata(), None, None);
+        contract.create_token("ipfs://three".to_string(), create_test_metadata(), None, None);
+    }
+
+    #[test]
+    fn test_voting_power_by_mode() {
+        let context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        assert_eq!(contract.get_voting_power(U128(10_000)), U128(10_000));
+
+        contract.set_voting_mode(VotingMode::Quadratic);
+        assert_eq!(contract.get_voting_power(U128(10_000)), U128(100));
+        assert_eq!(contract.get_voting_power(U128(MIN_STAKE_AMOUNT)), U128(Math::sqrt(MIN_STAKE_AMOUNT)));
+    }
+
+    #[test]
+    fn test_quadratic_vote_credits_voting_power() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_block(&mut context, 1, |c| c.set_voting_mode(VotingMode::Quadratic));
+        open_voting(&mut context, &mut contract);
+        let token_id = token_ids[0];
+
+        // Two votes from one account count as one combined stake
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 2);
+        contract.vote(token_id);
+        contract.vote(token_id);
+        assert_eq!(
+            contract.get_votes(token_id),
+            Some(contract.get_voting_power(U128(MIN_STAKE_AMOUNT * 4)))
+        );
+    }
+
+    #[test]
+    fn test_refund_for_token_left_queued() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 2);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+
+        // Status machine edge case: the token never shows as in voting
+        let mut token = contract.tokens.get(&token_ids[1]).unwrap();
+        token.status = TokenStatus::Queued;
+        contract.tokens.insert(&token_ids[1], &token);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        assert_eq!(contract.get_token(token_ids[1]).unwrap().status, TokenStatus::Lost);
+        // Bob is refunded; the winner's creator claims their share later
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, AccountId::try_from("bob.near".to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_pool_seed_split() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        contract.set_pool_seed_bps(2_500);
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 3);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        // 4 NEAR raised: 25% seeds the pool, 75% goes to the creator
+        let pool = contract.pools.get(&token_ids[0]).unwrap();
+        assert_eq!(pool.native_reserve, MIN_STAKE_AMOUNT);
+        assert!(pool.lp_total_supply > 0);
+
+        let creator = AccountId::try_from("creator.near".to_string()).unwrap();
+        assert_eq!(contract.get_creator_pending(creator), U128(MIN_STAKE_AMOUNT * 3));
+    }
+
+    #[test]
+    fn test_validate_token_lists_problems() {
+        let context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        assert!(contract.validate_token("ipfs://content".to_string(), create_test_metadata()).is_empty());
+
+        let mut metadata = create_test_metadata();
+        metadata.title = "x".repeat(101);
+        metadata.media = Some("https://media".to_string());
+        metadata.copies = Some(0);
+        assert_eq!(
+            contract.validate_token("https://content".to_string(), metadata),
+            vec![
+                "Invalid content hash protocol".to_string(),
+                "Title too long".to_string(),
+                "Invalid media protocol".to_string(),
+                "Copies must be greater than 0".to_string(),
+            ]
+        );
+
+        let mut metadata = create_test_metadata();
+        metadata.copies = Some(1_000_000_001);
+        contract.block_hash("ipfs://content".to_string());
+        assert_eq!(
+            contract.validate_token("ipfs://content".to_string(), metadata),
+            vec!["Too many copies".to_string(), "Content is blocked".to_string()]
+        );
+        assert_eq!(
+            contract.validate_token(String::new(), create_test_metadata()),
+            vec!["Content hash cannot be empty".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_winner_supply_capped() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        // The cap is lowered after creation, so only the clamp applies
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token(
+            "ipfs://content".to_string(),
+            create_test_metadata(),
+            Some(SupplySchedule::Fixed(1_000_000_000)),
+            None,
+        );
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_max_token_supply(U128(50_000));
+        contract.start_block(None, None, None);
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + 1);
+        testing_env!(context.build());
+        contract.update_block_phase();
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_id);
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        assert_eq!(contract.get_token(token_id).unwrap().total_supply, U128(50_000));
+    }
+
+    #[test]
+    fn test_remaining_winner_slots() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let contract = TokenBlocks::new("owner.near".to_string());
+        assert_eq!(contract.get_remaining_winner_slots(), None);
+
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 4);
+        contract.current_block.as_mut().unwrap().max_winners = 3;
+        assert_eq!(contract.get_remaining_winner_slots(), Some(3));
+
+        // Votes of 5, 3, 1, 1: the cutoff is 1, so only two slots are firm
+        for (token_id, stake) in token_ids.iter().zip([5, 3, 1, 1]) {
+            set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * stake);
+            contract.vote(*token_id);
+        }
+        assert_eq!(contract.get_remaining_winner_slots(), Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Refund not expired")]
+    fn test_sweep_before_expiry() {
+        let context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        contract.record_pending_refund(&alice, 500);
+
+        contract.sweep_unclaimed_refunds(alice);
+    }
+
+    #[test]
+    fn test_sweep_after_expiry() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let treasury = AccountId::try_from("treasury.near".to_string()).unwrap();
+        contract.set_treasury(treasury.clone());
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        contract.record_pending_refund(&alice, 500);
+
+        context.block_timestamp(DEFAULT_REFUND_EXPIRY_NS);
+        testing_env!(context.build());
+        assert_eq!(contract.sweep_unclaimed_refunds(alice.clone()), U128(500));
+        assert_eq!(contract.get_pending_refund(alice), None);
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, treasury);
+    }
+
+    #[test]
+    fn test_balance_breakdown() {
+        let mut context = get_context();
+        context.account_balance(10_000);
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        let mut pool = Pool::new(0, 1_000);
+        pool.initialize_liquidity(0, 3_000);
+        contract.save_pool(&pool);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        contract.record_pending_refund(&alice, 500);
+
+        let breakdown = contract.get_balance_breakdown();
+        assert_eq!(breakdown.account_balance, U128(10_000));
+        assert_eq!(breakdown.pool_native, U128(3_000));
+        assert_eq!(breakdown.pending_refunds, U128(500));
+        assert_eq!(breakdown.free, U128(6_500));
+        assert!(contract.audit().is_empty());
+    }
+
+    #[test]
+    fn test_dust_refunds_aggregate() {
+        let context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_min_refund_amount(U128(1_000));
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+
+        contract.refund_stake(alice.clone(), 600);
+        assert_eq!(get_created_receipts().len(), 0);
+        assert_eq!(contract.get_pending_refund(alice.clone()).unwrap().amount, U128(600));
+
+        // The second refund pushes the total over the threshold
+        contract.refund_stake(alice.clone(), 600);
+        assert_eq!(contract.get_pending_refund(alice.clone()), None);
+        assert_eq!(contract.total_pending_refunds, 0);
+        assert_eq!(transfers(), vec![("alice.near".to_string(), 1_200)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Payment token not accepted")]
+    fn test_unlisted_payment_token_rejected() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "fake-usd.near", 0);
+        contract.ft_on_transfer(
+            AccountId::try_from("alice.near".to_string()).unwrap(),
+            U128(1_000),
+            r#"{"token_id": 0, "amount": "100"}"#.to_string(),
+        );
+    }
+
+    #[test]
+    fn test_listed_payment_token_purchase() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+        let usdc = AccountId::try_from("usdc.near".to_string()).unwrap();
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://next".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "owner.near", 0);
+        contract.add_payment_token(usdc.clone(), PaymentConfig { decimals: 6, price_oracle: None });
+        contract.start_block(None, None, None);
+        contract.current_block.as_mut().unwrap().phase = BlockPhase::Public;
+        // Two stablecoin units per token
+        let mut pool = contract.pools.get(&token_ids[0]).unwrap();
+        pool.usdc_reserve = pool.token_reserve * 2;
+        contract.save_pool(&pool);
+
+        set_caller(&mut context, "usdc.near", 0);
+        let before = contract.get_token(token_ids[0]).unwrap().circulating_supply.0;
+        let unused = contract.ft_on_transfer(
+            AccountId::try_from("alice.near".to_string()).unwrap(),
+            U128(1_000),
+            format!(r#"{{"token_id": {}, "amount": "100"}}"#, token_ids[0]),
+        );
+        assert!(matches!(unused, PromiseOrValue::Value(U128(800))));
+        assert_eq!(contract.get_token(token_ids[0]).unwrap().circulating_supply.0, before + 100);
+        assert_eq!(contract.get_payment_tokens().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient payment")]
+    fn test_ft_purchase_requires_full_price() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+
+        set_caller(&mut context, "usdc.near", 0);
+        contract.ft_on_transfer(
+            AccountId::try_from("alice.near".to_string()).unwrap(),
+            U128(1),
+            format!(r#"{{"token_id": {}, "amount": "100000"}}"#, token_id),
+        );
+    }
+
+    #[test]
+    fn test_ft_purchase_priced_by_oracle() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+        let oracle_usdc = AccountId::try_from("ousdc.near".to_string()).unwrap();
+        set_caller(&mut context, "owner.near", 0);
+        contract.add_payment_token(
+            oracle_usdc.clone(),
+            PaymentConfig { decimals: 6, price_oracle: Some(AccountId::try_from("oracle.near".to_string()).unwrap()) },
+        );
+
+        set_caller(&mut context, "ousdc.near", 0);
+        let purchase = format!(r#"{{"token_id": {}, "amount": "1000"}}"#, token_id);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        let result = contract.ft_on_transfer(alice.clone(), U128(10u128.pow(30)), purchase.clone());
+        assert!(matches!(result, PromiseOrValue::Promise(_)));
+
+        // The oracle quotes one whole payment token at 2 NEAR
+        let price = U128(2 * 10u128.pow(24));
+        let required = contract.pools.get(&token_id).unwrap().calculate_native_required(1_000) * 10u128.pow(6) / price.0;
+        context.predecessor_account_id(ValidAccountId::try_from("contract.near".to_string()).unwrap());
+        near_sdk::test_utils::testing_env_with_promise_results(
+            context.build(),
+            near_sdk::PromiseResult::Successful(near_sdk::serde_json::to_vec(&price).unwrap()),
+        );
+        let usdc_reserve = contract.pools.get(&token_id).unwrap().usdc_reserve;
+        let unused = contract.on_ft_purchase_priced(
+            oracle_usdc,
+            alice,
+            near_sdk::serde_json::from_str(&purchase).unwrap(),
+            U128(10u128.pow(30)),
+        );
+        assert_eq!(unused, U128(10u128.pow(30) - required));
+        // Oracle-priced payments stay out of the stablecoin reserve
+        assert_eq!(contract.pools.get(&token_id).unwrap().usdc_reserve, usdc_reserve);
+    }
+
+    #[test]
+    #[should_panic(expected = "Another payment token is already priced without an oracle")]
+    fn test_single_oracle_less_payment_token() {
+        let mut context = get_context();
+        let (mut contract, _) = setup_stake_weighted_purchase(&mut context);
+        contract.add_payment_token(
+            AccountId::try_from("dai.near".to_string()).unwrap(),
+            PaymentConfig { decimals: 18, price_oracle: None },
+        );
+    }
+
+    #[test]
+    fn test_reference_stablecoin_scaled_by_decimals() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+        let usdc = AccountId::try_from("usdc.near".to_string()).unwrap();
+        contract.remove_payment_token(usdc);
+        contract.add_payment_token(
+            AccountId::try_from("dai.near".to_string()).unwrap(),
+            PaymentConfig { decimals: 18, price_oracle: None },
+        );
+        // Two 6-decimal stablecoin units per token
+        let mut pool = contract.pools.get(&token_id).unwrap();
+        pool.usdc_reserve = pool.token_reserve * 2;
+        contract.save_pool(&pool);
+        let usdc_reserve = pool.usdc_reserve;
+
+        // 100 tokens cost 200 units at 6 decimals, 200e12 at 18
+        set_caller(&mut context, "dai.near", 0);
+        let unused = contract.ft_on_transfer(
+            AccountId::try_from("alice.near".to_string()).unwrap(),
+            U128(10u128.pow(15)),
+            format!(r#"{{"token_id": {}, "amount": "100"}}"#, token_id),
+        );
+        assert!(matches!(unused, PromiseOrValue::Value(U128(800_000_000_000_000))));
+        // The 5% contribution lands back at 6 decimals
+        assert_eq!(contract.pools.get(&token_id).unwrap().usdc_reserve, usdc_reserve + 10);
+    }
+
+    #[test]
+    fn test_recent_tokens_newest_first() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        for i in 0..3 {
+            context.block_timestamp(i * 1_000);
+            testing_env!(context.build());
+            contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        }
+
+        let recent: Vec<TokenId> = contract.get_recent_tokens(2).iter().map(|t| t.id).collect();
+        assert_eq!(recent, vec![2, 1]);
+        assert_eq!(contract.get_token_age(0), Some(2_000));
+        assert_eq!(contract.get_token_age(7), None);
+    }
+
+    // Runs a one-token block that alice backs and returns the native
+    // transfers made when it wins, plus what's held for the creator
+    fn winner_voter_payouts(refund_winner_stakes: bool) -> (Vec<(String, Balance)>, U128) {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        contract.refund_winner_stakes = refund_winner_stakes;
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+        assert_eq!(contract.get_token(token_ids[0]).unwrap().status, TokenStatus::Winner);
+
+        let creator = AccountId::try_from("creator.near".to_string()).unwrap();
+        (transfers(), contract.get_creator_pending(creator))
+    }
+
+    #[test]
+    fn test_winner_stakes_absorbed_or_refunded() {
+        // Default: the stake funds the pool and creator, alice gets nothing back
+        let (paid, creator_pending) = winner_voter_payouts(false);
+        assert!(paid.iter().all(|(receiver, _)| receiver != "alice.near"));
+        assert!(creator_pending.0 > 0);
+
+        // Non-custodial: alice's stake comes back and nothing goes to the creator
+        let (paid, creator_pending) = winner_voter_payouts(true);
+        assert_eq!(paid, vec![("alice.near".to_string(), MIN_STAKE_AMOUNT)]);
+        assert_eq!(creator_pending, U128(0));
+    }
+
+    #[test]
+    fn test_standings_visible_before_blackout() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_standings_blackout(VOTING_DURATION / 4);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        assert_eq!(contract.get_current_standings()[0], (token_ids[0], U128(MIN_STAKE_AMOUNT)));
+        assert!(contract.get_winning_cutoff().is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Standings hidden until voting ends")]
+    fn test_standings_hidden_in_blackout() {
+        let mut context = get_context();
+        let (mut contract, _) = setup_voting_block(&mut context, 2);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_standings_blackout(VOTING_DURATION / 4);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION - 1);
+        testing_env!(context.build());
+        contract.get_current_standings();
+    }
+
+    #[test]
+    fn test_block_summary_empty_in_blackout() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_standings_blackout(VOTING_DURATION / 4);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION - 1);
+        testing_env!(context.build());
+        assert!(contract.get_block_summary().unwrap().standings.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Standings hidden until voting ends")]
+    fn test_block_votes_hidden_in_blackout() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_standings_blackout(VOTING_DURATION / 4);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION - 1);
+        testing_env!(context.build());
+        contract.get_votes(token_ids[0]);
+    }
+
+    #[test]
+    fn test_priority_eligibility() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        assert!(contract.is_priority_eligible(AccountId::try_from("alice.near".to_string()).unwrap()));
+        assert!(!contract.is_priority_eligible(AccountId::try_from("bob.near".to_string()).unwrap()));
+    }
+
+    // Runs a two-token block with a 5 NEAR creator bond where the first
+    // token wins. Returns the bond transfers and what's held for the
+    // winning creator.
+    fn settle_bonds() -> (Vec<(String, Balance)>, U128) {
+        let bond = 5 * MIN_STAKE_AMOUNT;
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_creator_bond(U128(bond));
+        contract.set_treasury(AccountId::try_from("treasury.near".to_string()).unwrap());
+        assert_eq!(contract.get_required_bond(), U128(bond));
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT + bond);
+        let winner = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "loser.near", CREATION_DEPOSIT + bond);
+        let loser = contract.create_token("ipfs://content2".to_string(), create_test_metadata(), None, None);
+        assert_eq!(contract.token_bonds.get(&winner), Some(bond));
+        assert_eq!(contract.token_bonds.get(&loser), Some(bond));
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + 1);
+        testing_env!(context.build());
+        contract.update_block_phase();
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(winner);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.finalize_voting();
+        // The winner's bond waits for settlement
+        assert_eq!(contract.token_bonds.keys_as_vector().to_vec(), vec![winner]);
+        assert!(contract.distribute(u32::MAX));
+        assert!(contract.token_bonds.is_empty());
+
+        let paid = transfers()
+            .into_iter()
+            .filter(|(_, deposit)| *deposit == bond)
+            .collect();
+        let creator = AccountId::try_from("creator.near".to_string()).unwrap();
+        (paid, contract.get_creator_pending(creator))
+    }
+
+    #[test]
+    fn test_creator_bond_refunded_on_win() {
+        // Held for the creator to claim alongside the unseeded half of
+        // alice's stake
+        let (paid, creator_pending) = settle_bonds();
+        assert_eq!(creator_pending, U128(5 * MIN_STAKE_AMOUNT + MIN_STAKE_AMOUNT / 2));
+        assert!(paid.iter().all(|(receiver, _)| receiver != "creator.near"));
+    }
+
+    #[test]
+    fn test_creator_bond_forfeited_on_loss() {
+        let (paid, _) = settle_bonds();
+        assert!(paid.contains(&("treasury.near".to_string(), 5 * MIN_STAKE_AMOUNT)));
+        assert!(paid.iter().all(|(receiver, _)| receiver != "loser.near"));
+    }
+
+    // Settles a winning token staked 1:3 by alice and bob, then opens a new
+    // block in its Public phase with usdc.near accepted as payment
+    fn setup_stake_weighted_purchase(context: &mut VMContextBuilder) -> (TokenBlocks, TokenId) {
+        let (mut contract, token_ids) = setup_voting_block(context, 1);
+        set_caller(context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+        set_caller(context, "bob.near", 3 * MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        set_caller(context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://next".to_string(), create_test_metadata(), None, None);
+        set_caller(context, "owner.near", 0);
+        contract.set_stake_weighted_priority(true);
+        contract.add_payment_token(
+            AccountId::try_from("usdc.near".to_string()).unwrap(),
+            PaymentConfig { decimals: 6, price_oracle: None },
+        );
+        contract.start_block(None, None, None);
+        contract.current_block.as_mut().unwrap().phase = BlockPhase::Public;
+
+        // One stablecoin unit per 1,000 tokens
+        let mut pool = contract.pools.get(&token_ids[0]).unwrap();
+        pool.usdc_reserve = pool.token_reserve / 1_000;
+        contract.save_pool(&pool);
+        (contract, token_ids[0])
+    }
+
+    #[test]
+    #[should_panic(expected = "Exceeds priority allocation")]
+    fn test_priority_purchase_capped_by_stake() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        let token = contract.tokens.get(&token_id).unwrap();
+        let allocation = (token.total_supply - token.pool_reserve) / 4;
+        assert_eq!(contract.get_priority_allocation(token_id, alice.clone()), U128(allocation));
+
+        contract.record_priority_purchase(token_id, &alice, allocation);
+        contract.record_priority_purchase(token_id, &alice, 1);
+    }
+
+    #[test]
+    fn test_public_purchase_ignores_allocation() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        let allocation = contract.get_priority_allocation(token_id, alice.clone()).0;
+
+        set_caller(&mut context, "usdc.near", 0);
+        contract.ft_on_transfer(
+            alice,
+            U128(10u128.pow(30)),
+            format!(r#"{{"token_id": {}, "amount": "{}"}}"#, token_id, allocation + 1),
+        );
+        assert!(contract.priority_purchases.is_empty());
+    }
+
+    #[test]
+    fn test_phase_math_uses_configured_durations() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_block_phases(BlockPhaseConfig {
+            accepting_tokens_duration: 10,
+            voting_duration: 20,
+            public_duration: 30,
+        });
+        assert_eq!(contract.get_durations(), (10, 20, 30));
+        assert_eq!(contract.get_block_info().0, 60);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+
+        let block = contract.current_block.as_ref().unwrap();
+        let voting_end = Time::get_block_end_time(block.start_time, 10 + 20);
+        assert_eq!(block.voting_end_time, voting_end);
+
+        let transitioned = |from: &str, to: &str| PhaseUpdateResult::Transitioned {
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+        let expected = [
+            (9, PhaseUpdateResult::Unchanged("AcceptingTokens".to_string())),
+            (10, transitioned("AcceptingTokens", "Voting")),
+            (30, transitioned("Voting", "Public")),
+            (60, transitioned("Public", "Completed")),
+        ];
+        for (now, result) in expected {
+            context.block_timestamp(now);
+            testing_env!(context.build());
+            assert_eq!(contract.update_block_phase(), result);
+        }
+    }
+
+    #[test]
+    fn test_keeper_paid_only_for_transitions() {
+        let reward = MIN_STAKE_AMOUNT / 100;
+        let mut context = get_context();
+        context.account_balance(100 * MIN_STAKE_AMOUNT);
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_keeper_reward(U128(reward));
+        set_caller(&mut context, "owner.near", reward);
+        contract.fund_keeper_rewards();
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+
+        // Nothing to advance yet
+        set_caller(&mut context, "keeper.near", 0);
+        assert_eq!(contract.update_block_phase(), PhaseUpdateResult::Unchanged("AcceptingTokens".to_string()));
+        assert!(get_created_receipts().is_empty());
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION);
+        testing_env!(context.build());
+        contract.update_block_phase();
+        assert_eq!(transfers(), vec![("keeper.near".to_string(), reward)]);
+        assert_eq!(contract.keeper_fund, 0);
+
+        // The fund is spent, so the next transition goes unpaid
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        assert_eq!(contract.update_block_phase(), PhaseUpdateResult::Transitioned {
+            from: "Voting".to_string(),
+            to: "Public".to_string(),
+        });
+        assert!(get_created_receipts().is_empty());
+    }
+
+    #[test]
+    fn test_total_staked_tracks_votes() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        assert_eq!(contract.get_total_staked(), Some(U128(0)));
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "bob.near", 2 * MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+
+        assert_eq!(contract.get_total_staked(), Some(U128(3 * MIN_STAKE_AMOUNT)));
+        assert!(contract.audit().is_empty());
+
+        contract.current_block.as_mut().unwrap().total_stakes += 1;
+        assert_eq!(contract.audit().len(), 1);
+    }
+
+    #[test]
+    fn test_require_media_toggle() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let metadata = create_test_metadata(); // has no media
+
+        contract.set_require_media(true);
+        assert_eq!(
+            contract.validate_token("ipfs://content".to_string(), metadata.clone()),
+            vec!["Media is required".to_string()]
+        );
+
+        contract.set_require_media(false);
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), metadata, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Media is required")]
+    fn test_require_media_rejects_token() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_require_media(true);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+    }
+
+    // Three tokens where creation order, market cap, volume and votes
+    // each rank them differently
+    fn setup_sortable_tokens(context: &mut VMContextBuilder) -> TokenBlocks {
+        let (mut contract, token_ids) = setup_voting_block(context, 3);
+        for (token_id, stake) in token_ids.iter().zip([2, 3, 1]) {
+            set_caller(context, "alice.near", stake * MIN_STAKE_AMOUNT);
+            contract.vote(*token_id);
+        }
+        for (token_id, (circulating, volume)) in token_ids.iter().zip([(100, 30), (300, 10), (200, 20)]) {
+            let mut token = contract.tokens.get(token_id).unwrap();
+            token.circulating_supply = circulating;
+            contract.tokens.insert(token_id, &token);
+            let mut pool = Pool::new(*token_id, 0);
+            pool.initialize_liquidity(1_000, 1_000);
+            pool.volume_24h = volume;
+            contract.save_pool(&pool);
+        }
+        contract
+    }
+
+    fn sorted_ids(contract: &TokenBlocks, sort_by: SortKey, descending: bool) -> Vec<TokenId> {
+        contract.get_tokens_sorted(sort_by, descending, 0, 10).iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn test_tokens_sorted_by_each_key() {
+        let mut context = get_context();
+        let contract = setup_sortable_tokens(&mut context);
+
+        // Created in the same block timestamp, so ids break the tie
+        assert_eq!(sorted_ids(&contract, SortKey::CreatedAt, false), vec![0, 1, 2]);
+        assert_eq!(sorted_ids(&contract, SortKey::MarketCap, true), vec![1, 2, 0]);
+        assert_eq!(sorted_ids(&contract, SortKey::Volume24h, true), vec![0, 2, 1]);
+        assert_eq!(sorted_ids(&contract, SortKey::Votes, true), vec![1, 0, 2]);
+        assert_eq!(sorted_ids(&contract, SortKey::Votes, false), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn test_tokens_sorted_paginates() {
+        let mut context = get_context();
+        let contract = setup_sortable_tokens(&mut context);
+        let page: Vec<TokenId> = contract.get_tokens_sorted(SortKey::Votes, true, 1, 1).iter().map(|t| t.id).collect();
+        assert_eq!(page, vec![0]);
+    }
+
+    #[test]
+    fn test_phase_boundaries_match_observed_phases() {
+        let mut context = get_context();
+        let (mut contract, _) = setup_voting_block(&mut context, 1);
+        let (accepting_end, voting_end, public_end, complete) = contract.get_phase_boundaries().unwrap();
+        assert_eq!(accepting_end, ACCEPTING_TOKENS_DURATION);
+        assert_eq!(voting_end, ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+
+        let block = contract.current_block.as_mut().unwrap();
+        for (boundary, before, after) in [
+            (accepting_end, BlockPhase::AcceptingTokens, BlockPhase::Voting),
+            (voting_end, BlockPhase::Voting, BlockPhase::Public),
+            (public_end, BlockPhase::Public, BlockPhase::Completed),
+        ] {
+            block.update_phase(boundary - 1);
+            assert_eq!(block.phase, before);
+            block.update_phase(boundary);
+            assert_eq!(block.phase, after);
+        }
+        assert_eq!(complete, public_end);
+
+        contract.current_block = None;
+        assert_eq!(contract.get_phase_boundaries(), None);
+    }
+
+    #[test]
+    fn test_partial_fill_refunds_excess() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+        let available = contract.tokens.get(&token_id).unwrap().available_for_purchase();
+        let pool = contract.pools.get(&token_id).unwrap();
+        let required = pool.calculate_native_required(available);
+        let payment = pool.calculate_native_required(available + 1_000) + 1;
+
+        set_caller(&mut context, "alice.near", payment);
+        let filled = contract.purchase_with_native(token_id, U128(available + 1_000), true);
+        assert_eq!(filled, available);
+        assert_eq!(contract.tokens.get(&token_id).unwrap().available_for_purchase(), 0);
+
+        let refund: Vec<(String, Balance)> = transfers()
+            .into_iter()
+            .filter(|(receiver, _)| receiver == "alice.near")
+            .collect();
+        assert_eq!(refund, vec![("alice.near".to_string(), payment - required)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient tokens available")]
+    fn test_oversized_purchase_reverts_without_partial() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+        let available = contract.tokens.get(&token_id).unwrap().available_for_purchase();
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.purchase_with_native(token_id, U128(available + 1), false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool liquidity below purchase minimum")]
+    fn test_purchase_from_thin_pool_reverts() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+        let native_reserve = contract.pools.get(&token_id).unwrap().native_reserve;
+        contract.set_min_pool_liquidity_for_purchase(U128(native_reserve + 1));
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.purchase_with_native(token_id, U128(1), false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool liquidity below purchase minimum")]
+    fn test_ft_purchase_from_thin_pool_reverts() {
+        let mut context = get_context();
+        let (mut contract, token_id) = setup_stake_weighted_purchase(&mut context);
+        let native_reserve = contract.pools.get(&token_id).unwrap().native_reserve;
+        contract.set_min_pool_liquidity_for_purchase(U128(native_reserve + 1));
+
+        set_caller(&mut context, "usdc.near", 0);
+        contract.ft_on_transfer(
+            AccountId::try_from("alice.near".to_string()).unwrap(),
+            U128(10u128.pow(30)),
+            format!(r#"{{"token_id": {}, "amount": "1"}}"#, token_id),
+        );
+    }
+
+    #[test]
+    fn test_winning_creator_claims_pending_funds() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * 4);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        // Half of the 4 NEAR raised seeds the pool, half is owed to the creator
+        let creator = AccountId::try_from("creator.near".to_string()).unwrap();
+        assert_eq!(contract.get_creator_pending(creator.clone()), U128(MIN_STAKE_AMOUNT * 2));
+        assert_eq!(contract.get_balance_breakdown().creator_pending, U128(MIN_STAKE_AMOUNT * 2));
+
+        set_caller(&mut context, "creator.near", 0);
+        assert_eq!(contract.claim_creator_funds(), U128(MIN_STAKE_AMOUNT * 2));
+        assert_eq!(contract.get_creator_pending(creator.clone()), U128(0));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, creator);
+    }
+
+    // Plays the verifier's `is_verified` answer back into this contract's callback
+    fn answer_verifier(context: &mut VMContextBuilder, verified: bool) {
+        context
+            .predecessor_account_id(ValidAccountId::try_from("contract.near".to_string()).unwrap())
+            .attached_deposit(0);
+        near_sdk::test_utils::testing_env_with_promise_results(
+            context.build(),
+            near_sdk::PromiseResult::Successful(verified.to_string().into_bytes()),
+        );
+    }
+
+    #[test]
+    fn test_identity_verifier_gates_votes() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_identity_verifier(Some("verifier.near".to_string()));
+
+        // Both votes wait on the verifier
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        assert!(!contract.vote(token_ids[0]));
+        assert_eq!(get_created_receipts()[0].receiver_id, "verifier.near".to_string());
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        assert!(!contract.vote(token_ids[0]));
+        assert_eq!(contract.get_total_staked(), Some(U128(0)));
+
+        // The verifier approves alice and rejects bob
+        answer_verifier(&mut context, true);
+        assert!(contract.on_vote_verified("alice.near".to_string(), token_ids[0], U128(MIN_STAKE_AMOUNT)));
+        answer_verifier(&mut context, false);
+        assert!(!contract.on_vote_verified("bob.near".to_string(), token_ids[0], U128(MIN_STAKE_AMOUNT)));
+
+        assert_eq!(contract.get_total_staked(), Some(U128(MIN_STAKE_AMOUNT)));
+        assert!(contract.stakes.get(&"bob.near".to_string()).is_none());
+        assert_eq!(transfers(), vec![("bob.near".to_string(), MIN_STAKE_AMOUNT)]);
+    }
+
+    #[test]
+    fn test_verified_vote_refunded_after_voting_ends() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_identity_verifier(Some("verifier.near".to_string()));
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        // The verifier answers after voting closed
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        answer_verifier(&mut context, true);
+        assert!(!contract.on_vote_verified("alice.near".to_string(), token_ids[0], U128(MIN_STAKE_AMOUNT)));
+        assert!(contract.stakes.get(&"alice.near".to_string()).is_none());
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, "alice.near".to_string());
+    }
+
+    #[test]
+    fn test_verified_creation_rechecks_daily_limit() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_identity_verifier(Some("verifier.near".to_string()));
+        contract.max_tokens_per_account_per_day = 1;
+
+        // Both requests pass the early check since neither is stored yet
+        set_caller(&mut context, "alice.near", CREATION_DEPOSIT);
+        let first = contract.create_token("ipfs://first".to_string(), create_test_metadata(), None, None);
+        let second = contract.create_token("ipfs://second".to_string(), create_test_metadata(), None, None);
+
+        answer_verifier(&mut context, true);
+        assert!(contract.on_create_verified(
+            "alice.near".to_string(), first, "ipfs://first".to_string(),
+            create_test_metadata(), None, None, U128(CREATION_DEPOSIT),
+        ));
+        answer_verifier(&mut context, true);
+        assert!(!contract.on_create_verified(
+            "alice.near".to_string(), second, "ipfs://second".to_string(),
+            create_test_metadata(), None, None, U128(CREATION_DEPOSIT),
+        ));
+        assert!(contract.get_token(second).is_none());
+        assert_eq!(transfers().last(), Some(&("alice.near".to_string(), CREATION_DEPOSIT)));
+    }
+
+    #[test]
+    fn test_identity_verifier_gates_creation() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_identity_verifier(Some("verifier.near".to_string()));
+
+        set_caller(&mut context, "alice.near", CREATION_DEPOSIT);
+        let alice_token = contract.create_token("ipfs://alice".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "bob.near", CREATION_DEPOSIT);
+        let bob_token = contract.create_token("ipfs://bob".to_string(), create_test_metadata(), None, None);
+        assert_ne!(alice_token, bob_token);
+        assert!(contract.get_token(alice_token).is_none());
+
+        answer_verifier(&mut context, true);
+        assert!(contract.on_create_verified(
+            "alice.near".to_string(), alice_token, "ipfs://alice".to_string(),
+            create_test_metadata(), None, None, U128(CREATION_DEPOSIT),
+        ));
+        answer_verifier(&mut context, false);
+        assert!(!contract.on_create_verified(
+            "bob.near".to_string(), bob_token, "ipfs://bob".to_string(),
+            create_test_metadata(), None, None, U128(CREATION_DEPOSIT),
+        ));
+
+        assert_eq!(contract.get_token(alice_token).unwrap().creator, "alice.near".to_string());
+        assert!(contract.get_token(bob_token).is_none());
+        assert_eq!(transfers().last(), Some(&("bob.near".to_string(), CREATION_DEPOSIT)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Supply schedule exceeds max supply")]
+    fn test_schedule_above_max_supply_rejected() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token(
+            "ipfs://content".to_string(),
+            create_test_metadata(),
+            Some(SupplySchedule::Fixed(DEFAULT_MAX_TOKEN_SUPPLY + 1)),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_supply_schedule_realized_on_win() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let schedule = SupplySchedule::Linear { base: 10_000, step: 500 };
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token(
+            "ipfs://content".to_string(),
+            create_test_metadata(),
+            Some(schedule.clone()),
+            None,
+        );
+        let view = contract.get_supply_schedule(token_id);
+        assert_eq!(view.schedule, schedule);
+        assert_eq!(view.total_supply, None);
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + 1);
+        testing_env!(context.build());
+        contract.update_block_phase();
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_id);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_id);
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+
+        let view = contract.get_supply_schedule(token_id);
+        assert_eq!(view.schedule, schedule);
+        assert_eq!(view.total_supply, Some(U128(11_000)));
+    }
+
+    #[test]
+    fn test_vote_on_others_token_with_self_vote_disabled() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_block(&mut context, 1, |c| c.set_allow_self_vote(false));
+        open_voting(&mut context, &mut contract);
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        assert!(contract.vote(token_ids[0]));
+        assert_eq!(contract.get_total_staked(), Some(U128(MIN_STAKE_AMOUNT)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Creators cannot vote on their own tokens")]
+    fn test_self_vote_rejected_when_disabled() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_block(&mut context, 1, |c| c.set_allow_self_vote(false));
+        open_voting(&mut context, &mut contract);
+
+        set_caller(&mut context, "creator.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+    }
+
+    #[test]
+    fn test_result_callback_receives_winners() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        contract.current_block.as_mut().unwrap().max_winners = 1;
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_result_callback(Some("nft.near".to_string()));
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[1]);
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.finalize_voting();
+
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|receipt| receipt.receiver_id == "nft.near".to_string())
+            .expect("Receiver not called");
+        match receipt.actions.first() {
+            Some(near_sdk::mock::VmAction::FunctionCall { function_name, args, .. }) => {
+                assert_eq!(function_name, "on_block_finalized");
+                let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(args).unwrap();
+                assert_eq!(args["winners"], near_sdk::serde_json::json!([token_ids[1]]));
+            }
+            _ => panic!("Expected a function call"),
+        }
+    }
+
+    #[test]
+    fn test_result_callback_failure_only_logged() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        context.predecessor_account_id(ValidAccountId::try_from("contract.near".to_string()).unwrap());
+        near_sdk::test_utils::testing_env_with_promise_results(context.build(), near_sdk::PromiseResult::Failed);
+        contract.on_result_callback_resolved("nft.near".to_string(), vec![3]);
+
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].contains("result_callback_failed"));
+    }
+
+    #[test]
+    fn test_can_join_current_block_while_accepting() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let contract = TokenBlocks::new("owner.near".to_string());
+        assert!(!contract.can_join_current_block());
+
+        let (mut contract, _) = setup_block(&mut context, 1, |_| {});
+        assert!(contract.can_join_current_block());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        assert!(contract.current_block.as_ref().unwrap().tokens.contains(&token_id));
+        assert!(contract.get_queued_tokens().is_empty());
+    }
+
+    #[test]
+    fn test_cannot_join_current_block_after_accepting() {
+        let mut context = get_context();
+        let (contract, _) = setup_voting_block(&mut context, 1);
+        assert!(!contract.can_join_current_block());
+
+        // Still closed at the boundary, before the phase is advanced
+        let mut context = get_context();
+        let (mut contract, _) = setup_block(&mut context, 1, |_| {});
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION);
+        testing_env!(context.build());
+        assert!(!contract.can_join_current_block());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        assert!(!contract.current_block.as_ref().unwrap().tokens.contains(&token_id));
+        assert_eq!(contract.get_queued_tokens(), vec![token_id]);
+    }
+
+    #[test]
+    fn test_cancel_block_in_batches() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 2);
+        for i in 0..30 {
+            set_caller(&mut context, &format!("voter{}.near", i), MIN_STAKE_AMOUNT);
+            contract.vote(token_ids[i % 2]);
+        }
+
+        set_caller(&mut context, "owner.near", 0);
+        assert_eq!(contract.get_cancel_progress(), None);
+        assert!(!contract.cancel_block(8));
+        assert_eq!(contract.get_cancel_progress(), Some((8, 30)));
+        assert!(!contract.cancel_block(8));
+        assert!(!contract.cancel_block(8));
+        assert_eq!(contract.get_cancel_progress(), Some((24, 30)));
+        assert!(contract.current_block.is_some());
+        assert!(contract.cancel_block(8));
+
+        assert_eq!(contract.get_cancel_progress(), None);
+        assert!(contract.current_block.is_none());
+        assert_eq!(get_created_receipts().len(), 30);
+        assert!(contract.stakes.is_empty());
+        assert!(!contract.is_priority_eligible("voter0.near".to_string()));
+        assert_eq!(contract.last_block_end, Some(ACCEPTING_TOKENS_DURATION + 1));
+        for token_id in token_ids {
+            assert_eq!(contract.get_votes(token_id), None);
+            assert_eq!(contract.tokens.get(&token_id).unwrap().status, TokenStatus::Queued);
+        }
+    }
+
+    #[test]
+    fn test_cancel_block_in_public_phase() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION + 1);
+        testing_env!(context.build());
+        contract.update_block_phase();
+        assert_eq!(contract.tokens.get(&token_ids[0]).unwrap().status, TokenStatus::Public);
+
+        set_caller(&mut context, "owner.near", 0);
+        assert!(contract.cancel_block(10));
+        assert_eq!(contract.tokens.get(&token_ids[0]).unwrap().status, TokenStatus::Queued);
+        assert_eq!(contract.get_queued_tokens(), token_ids);
+    }
+
+    #[test]
+    fn test_cancel_block_while_accepting() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_block(&mut context, 3, |_| {});
+
+        // A token that joined while accepting is still Queued; a flagged
+        // one stays out of the queue
+        let mut joined = contract.tokens.get(&token_ids[1]).unwrap();
+        joined.status = TokenStatus::Queued;
+        contract.tokens.insert(&token_ids[1], &joined);
+        contract.flag_spam(token_ids[2]);
+
+        assert!(contract.cancel_block(10));
+        assert!(contract.current_block.is_none());
+        let mut queued = contract.get_queued_tokens();
+        queued.sort();
+        assert_eq!(queued, vec![token_ids[0], token_ids[1]]);
+        assert_eq!(contract.tokens.get(&token_ids[0]).unwrap().status, TokenStatus::Queued);
+        assert_eq!(contract.tokens.get(&token_ids[2]).unwrap().status, TokenStatus::Removed);
+    }
+
+    #[test]
+    fn test_cannot_join_block_being_cancelled() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        for i in 0..2 {
+            set_caller(&mut context, &format!("voter{}.near", i), MIN_STAKE_AMOUNT);
+            contract.vote(token_ids[0]);
+        }
+        contract.current_block.as_mut().unwrap().phase = BlockPhase::AcceptingTokens;
+        contract.current_block.as_mut().unwrap().accepting_tokens_duration = u64::MAX / 2;
+        assert!(contract.can_join_current_block());
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.cancel_block(1);
+        assert!(!contract.can_join_current_block());
+    }
+
+    #[test]
+    #[should_panic(expected = "Block is being cancelled")]
+    fn test_vote_during_cancellation() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        for i in 0..2 {
+            set_caller(&mut context, &format!("voter{}.near", i), MIN_STAKE_AMOUNT);
+            contract.vote(token_ids[0]);
+        }
+        set_caller(&mut context, "owner.near", 0);
+        contract.cancel_block(1);
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+    }
+
+    #[test]
+    fn test_votes_needed_for_rank() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 3);
+        for (token_id, stake) in token_ids.iter().zip([5, 3, 1]) {
+            set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT * stake);
+            contract.vote(*token_id);
+        }
+
+        assert_eq!(contract.votes_needed_for_rank(token_ids[0], 1), U128(0));
+        assert_eq!(contract.votes_needed_for_rank(token_ids[1], 3), U128(0));
+        let needed = contract.votes_needed_for_rank(token_ids[2], 1);
+        assert_eq!(needed, U128(MIN_STAKE_AMOUNT * 4 + 1));
+
+        // Staking exactly the delta takes first place
+        set_caller(&mut context, "bob.near", needed.0);
+        contract.vote(token_ids[2]);
+        assert_eq!(contract.get_current_standings()[0].0, token_ids[2]);
+        assert_eq!(contract.votes_needed_for_rank(token_ids[2], 1), U128(0));
+    }
+
+    #[test]
+    fn test_priority_purchase_cooldown() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_priority_purchase_cooldown(1_000);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+
+        context.block_timestamp(5_000);
+        testing_env!(context.build());
+        contract.record_priority_purchase_time(0, &alice);
+        // Other tokens have their own window
+        contract.record_priority_purchase_time(1, &alice);
+
+        context.block_timestamp(6_000);
+        testing_env!(context.build());
+        contract.record_priority_purchase_time(0, &alice);
+    }
+
+    #[test]
+    #[should_panic(expected = "Priority purchase cooldown active")]
+    fn test_priority_purchase_within_cooldown() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.set_priority_purchase_cooldown(1_000);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+
+        context.block_timestamp(5_000);
+        testing_env!(context.build());
+        contract.record_priority_purchase_time(0, &alice);
+
+        context.block_timestamp(5_999);
+        testing_env!(context.build());
+        contract.record_priority_purchase_time(0, &alice);
+    }
+
+    #[test]
+    fn test_export_positions() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+        credit_balance(&mut contract.token_balances, &alice, 7, 500);
+        contract.record_pending_refund(&alice, 40);
+
+        let positions = contract.export_positions(alice.clone(), 0, 10);
+        assert_eq!(positions, vec![
+            PositionEntry { kind: PositionKind::TokenBalance, token_id: Some(7), amount: U128(500) },
+            PositionEntry { kind: PositionKind::Stake, token_id: Some(token_ids[0]), amount: U128(MIN_STAKE_AMOUNT) },
+            PositionEntry { kind: PositionKind::PendingRefund, token_id: None, amount: U128(40) },
+        ]);
+        assert_eq!(contract.export_positions(alice.clone(), 1, 1), positions[1..2].to_vec());
+        assert!(contract.export_positions(alice, 3, 10).is_empty());
+    }
+
+    #[test]
+    fn test_seconds_until_next_phase() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        assert_eq!(contract.get_seconds_until_next_phase(), None);
+
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+
+        context.block_timestamp(1);
+        testing_env!(context.build());
+        let remaining = contract.get_time_remaining_in_phase().unwrap();
+        assert_eq!(contract.get_seconds_until_next_phase(), Some(remaining / 1_000_000_000));
+        assert_eq!(remaining % 1_000_000_000, 999_999_999);
+
+        // Saturates once the boundary has passed
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION * 2);
+        testing_env!(context.build());
+        assert_eq!(contract.get_seconds_until_next_phase(), Some(0));
+    }
+
+    #[test]
+    fn test_creator_wins_across_blocks() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let mut block_start = 0;
+        let mut winners = Vec::new();
+
+        for round in 0..2 {
+            set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+            let token_id = contract.create_token(format!("ipfs://content{}", round), create_test_metadata(), None, None);
+            set_caller(&mut context, "owner.near", 0);
+            contract.start_block(None, None, None);
+            context.block_timestamp(block_start + ACCEPTING_TOKENS_DURATION + 1);
+            testing_env!(context.build());
+            contract.update_block_phase();
+
+            set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+            contract.vote(token_id);
+            context.block_timestamp(block_start + ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+            testing_env!(context.build());
+            contract.process_voting_results();
+
+            winners.push(token_id);
+            block_start += BLOCK_DURATION;
+            context.block_timestamp(block_start);
+        }
+
+        let wins: Vec<TokenId> = contract.get_creator_wins("creator.near".to_string())
+            .iter()
+            .map(|token| token.id)
+            .collect();
+        assert_eq!(wins, winners);
+        assert!(contract.get_creator_wins("alice.near".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_early_vote_bonus_decays() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_block(&mut context, 2, |c| c.set_early_vote_bonus(5_000));
+        let (early, late) = (token_ids[0], token_ids[1]);
+
+        // Right as voting opens: the full 50% bonus
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION);
+        testing_env!(context.build());
+        contract.update_block_phase();
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(early);
+
+        // Three quarters of the way through: a 12.5% bonus
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION / 4 * 3);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(late);
+
+        assert_eq!(contract.get_votes(early), Some(U128(MIN_STAKE_AMOUNT * 3 / 2)));
+        assert_eq!(contract.get_votes(late), Some(U128(MIN_STAKE_AMOUNT + MIN_STAKE_AMOUNT / 8)));
+        // Stakes stay raw, so refunds are unaffected
+        assert_eq!(contract.get_user_stakes("alice.near".to_string()), Some(U128(MIN_STAKE_AMOUNT)));
+        assert_eq!(contract.get_user_stakes("bob.near".to_string()), Some(U128(MIN_STAKE_AMOUNT)));
+    }
+
+    #[test]
+    fn test_preview_next_block_matches_start() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.max_tokens_per_block = 3;
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT * 5);
+        contract.create_tokens((0..5)
+            .map(|i| (format!("ipfs://content{}", i), create_test_metadata()))
+            .collect());
+
+        // Oldest first by default
+        let preview = contract.preview_next_block();
+        assert_eq!(preview, vec![0, 1, 2]);
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+        assert_eq!(contract.current_block.as_ref().unwrap().tokens, preview);
+        assert_eq!(contract.preview_next_block(), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_lifo_queue_order_takes_newest_first() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.max_tokens_per_block = 3;
+        contract.set_queue_order(QueueOrder::Lifo);
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT * 5);
+        contract.create_tokens((0..5)
+            .map(|i| (format!("ipfs://content{}", i), create_test_metadata()))
+            .collect());
+
+        assert_eq!(contract.preview_next_block(), vec![4, 3, 2]);
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, None, None);
+        assert_eq!(contract.current_block.as_ref().unwrap().tokens, vec![4, 3, 2]);
+        assert_eq!(contract.get_queued_tokens(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_low_participation_extends_voting_once() {
+        let grace = VOTING_DURATION / 2;
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_min_participation(U128(10 * MIN_STAKE_AMOUNT), grace);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        contract.process_voting_results();
+        assert!(contract.current_block.as_ref().unwrap().voting_extended);
+        assert!(get_logs().iter().any(|log| log.contains("voting_extended")));
+
+        // Voting is open again during the grace period
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        // Still short of the threshold, but this time the block finalizes
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION + grace);
+        testing_env!(context.build());
+        contract.process_voting_results();
+        assert!(contract.current_block.is_none());
+        assert_eq!(contract.get_all_winners(0, 10)[0].id, token_ids[0]);
+    }
+
+    #[test]
+    fn test_phase_update_extends_low_participation_block() {
+        let grace = PUBLIC_DURATION / 2;
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_min_participation(U128(10 * MIN_STAKE_AMOUNT), grace);
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_ids[0]);
+
+        let now = ACCEPTING_TOKENS_DURATION + VOTING_DURATION + PUBLIC_DURATION;
+        context.block_timestamp(now);
+        testing_env!(context.build());
+        // Voting reopens for the grace period, counted from now, instead of
+        // the block completing
+        assert_eq!(contract.update_block_phase(), PhaseUpdateResult::Unchanged("Voting".to_string()));
+        let block = contract.current_block.as_ref().unwrap();
+        assert!(block.voting_extended);
+        assert_eq!(block.voting_end_time, now + grace);
+        assert_eq!(block.phase_boundaries().2, now + grace + PUBLIC_DURATION);
+        assert!(contract.pending_distribution.is_none());
+
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        assert!(contract.vote(token_ids[0]));
+    }
+
+    #[test]
+    fn test_get_role() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.add_admin("admin.near".to_string());
+
+        assert_eq!(contract.get_role("owner.near".to_string()), Role::Owner);
+        assert_eq!(contract.get_role("admin.near".to_string()), Role::Admin);
+        assert_eq!(contract.get_role("random.near".to_string()), Role::User);
+
+        contract.remove_admin("admin.near".to_string());
+        assert_eq!(contract.get_role("admin.near".to_string()), Role::User);
+    }
+
+    #[test]
+    fn test_admin_can_moderate() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        contract.add_admin("admin.near".to_string());
+
+        set_caller(&mut context, "admin.near", 0);
+        contract.block_hash("ipfs://spam".to_string());
+        contract.set_pause_flags(PauseFlags { creation: true, ..Default::default() });
+        assert!(contract.blocked_hashes.contains(&"ipfs://spam".to_string()));
+        assert!(contract.get_pause_flags().creation);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner or an admin can call this method")]
+    fn test_user_cannot_moderate() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+
+        set_caller(&mut context, "random.near", 0);
+        contract.block_hash("ipfs://spam".to_string());
+    }
+
+    #[test]
+    fn test_withdraw_vote_pays_penalty_to_treasury() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_withdraw_penalty_bps(500);
+        contract.set_treasury("treasury.near".to_string());
+
+        let stake = 20 * MIN_STAKE_AMOUNT;
+        set_caller(&mut context, "alice.near", stake);
+        contract.vote(token_ids[0]);
+        set_caller(&mut context, "alice.near", 0);
+        let refunded = contract.withdraw_vote(token_ids[0]);
+        assert_eq!(refunded, U128(stake * 95 / 100));
+
+        assert_eq!(transfers(), vec![
+            ("treasury.near".to_string(), stake * 5 / 100),
+            ("alice.near".to_string(), stake * 95 / 100),
+        ]);
+        assert_eq!(contract.votes.get(&token_ids[0]).unwrap().total_votes, 0);
+        assert_eq!(contract.current_block.as_ref().unwrap().total_stakes, 0);
+    }
+
+    #[test]
+    fn test_withdraw_vote_takes_back_bonus() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_block(&mut context, 1, |c| c.set_early_vote_bonus(5_000));
+        let token_id = token_ids[0];
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION);
+        testing_env!(context.build());
+        contract.update_block_phase();
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_id);
+        set_caller(&mut context, "bob.near", MIN_STAKE_AMOUNT);
+        contract.vote(token_id);
+        set_caller(&mut context, "alice.near", 0);
+        contract.withdraw_vote(token_id);
+
+        // Only bob's stake and bonus are left
+        let vote_info = contract.votes.get(&token_id).unwrap();
+        assert_eq!(vote_info.total_votes, MIN_STAKE_AMOUNT * 3 / 2);
+        assert_eq!(vote_info.bonus_votes, MIN_STAKE_AMOUNT / 2);
+        assert!(vote_info.voter_bonus.get(&"alice.near".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Trading is paused")]
+    fn test_trading_paused_while_voting_open() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 1);
+        set_caller(&mut context, "owner.near", 0);
+        contract.set_pause_flags(PauseFlags { trading: true, ..Default::default() });
+        assert!(contract.get_pause_flags().trading);
+
+        set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+        assert!(contract.vote(token_ids[0]));
+        assert_eq!(contract.votes.get(&token_ids[0]).unwrap().total_votes, MIN_STAKE_AMOUNT);
+
+        contract.swap_native_for_tokens(token_ids[0], U128(0));
+    }
+
+    #[test]
+    fn test_collection_views_keep_id_order() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 3);
+        for &token_id in &token_ids {
+            set_caller(&mut context, "alice.near", MIN_STAKE_AMOUNT);
+            contract.vote(token_id);
+        }
+        // Removing the first stake moves the last one into its slot
+        set_caller(&mut context, "alice.near", 0);
+        contract.withdraw_vote(token_ids[0]);
+
+        let alice = "alice.near".to_string();
+        let positions = contract.export_positions(alice.clone(), 0, 10);
+        assert_eq!(positions, contract.export_positions(alice, 0, 10));
+        let staked: Vec<Option<TokenId>> = positions.iter().map(|position| position.token_id).collect();
+        assert_eq!(staked, vec![Some(token_ids[1]), Some(token_ids[2])]);
+
+        let created: Vec<TokenId> = contract.get_tokens_by_creator("creator.near".to_string())
+            .iter()
+            .map(|token| token.id)
+            .collect();
+        assert_eq!(created, token_ids);
+    }
+
+    #[test]
+    fn test_finalize_batch_count_matches_distribution() {
+        let mut context = get_context();
+        let (mut contract, token_ids) = setup_voting_block(&mut context, 3);
+        for (voter, &token_id) in ["alice.near", "bob.near", "carol.near", "dave.near", "erin.near"]
+            .iter()
+            .zip(token_ids.iter().cycle())
+        {
+            set_caller(&mut context, voter, MIN_STAKE_AMOUNT);
+            contract.vote(token_id);
+        }
+
+        context.block_timestamp(ACCEPTING_TOKENS_DURATION + VOTING_DURATION);
+        testing_env!(context.build());
+        let predicted = contract.get_finalize_batch_count(2);
+        contract.finalize_voting();
+        assert_eq!(contract.get_finalize_batch_count(2), predicted);
+
+        let mut batches = 0;
+        loop {
+            batches += 1;
+            if contract.distribute(2) {
+                break;
+            }
+        }
+        assert_eq!(batches, predicted);
+        assert_eq!(contract.get_finalize_batch_count(2), 0);
+    }
+
+    #[test]
+    fn test_anyone_can_start_block_with_defaults() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+
+        set_caller(&mut context, "keeper.near", 0);
+        contract.start_block(None, None, None);
+        assert!(contract.current_block.is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Phase durations must be positive")]
+    fn test_zero_duration_override_rejected() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        set_caller(&mut context, "creator.near", CREATION_DEPOSIT);
+        contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+
+        set_caller(&mut context, "owner.near", 0);
+        contract.start_block(None, Some(0), None);
+    }
+}
@@ -41,10 +41,10 @@ impl Math {
             return 0;
         }
         
-        let amount_with_fee = amount_a * 997; // 0.3% fee
-        let numerator = amount_with_fee * reserve_b;
-        let denominator = reserve_a * 1000 + amount_with_fee;
-        numerator / denominator
+        let amount_with_fee = U256::from(amount_a) * U256::from(997); // 0.3% fee
+        let numerator = amount_with_fee * U256::from(reserve_b);
+        let denominator = U256::from(reserve_a) * U256::from(1000) + amount_with_fee;
+        (numerator / denominator).as_u128()
     }
 
     /// Calculates price impact as a percentage
@@ -57,10 +57,10 @@ impl Math {
             return 0.0;
         }
 
-        let amount_with_fee = amount_in * 997 / 1000; // 0.3% fee
-        let numerator = amount_with_fee * reserve_out;
-        let denominator = reserve_in * 1000 + amount_with_fee * 997;
-        let amount_out = numerator / denominator;
+        let amount_with_fee = U256::from(amount_in) * U256::from(997) / U256::from(1000); // 0.3% fee
+        let numerator = amount_with_fee * U256::from(reserve_out);
+        let denominator = U256::from(reserve_in) * U256::from(1000) + amount_with_fee * U256::from(997);
+        let amount_out = (numerator / denominator).as_u128();
         
         let initial_price = reserve_out as f64 / reserve_in as f64;
         let final_price = (reserve_out - amount_out) as f64 / 
@@ -79,12 +79,12 @@ impl Math {
     ) -> Balance {
         if total_supply == 0 {
             // Initial liquidity provision
-            (amount_a as f64 * amount_b as f64).sqrt() as Balance
+            Math::sqrt_product(amount_a, amount_b)
         } else {
             // Subsequent liquidity provision
             std::cmp::min(
-                amount_a * total_supply / reserve_a,
-                amount_b * total_supply / reserve_b
+                Math::calculate_share(amount_a, reserve_a, total_supply),
+                Math::calculate_share(amount_b, reserve_b, total_supply)
             )
         }
     }
@@ -98,10 +98,15 @@ impl Math {
     ) -> (Balance, Balance) {
         assert!(lp_tokens <= total_supply, "Insufficient LP tokens");
         
-        let token_a_amount = lp_tokens * reserve_a / total_supply;
-        let token_b_amount = lp_tokens * reserve_b / total_supply;
+        if total_supply == 0 {
+            return (0, 0);
+        }
+        
+        // lp_tokens * reserve overflows u128 once both are at 1e24 scale
+        let token_a_amount = U256::from(lp_tokens) * U256::from(reserve_a) / U256::from(total_supply);
+        let token_b_amount = U256::from(lp_tokens) * U256::from(reserve_b) / U256::from(total_supply);
         
-        (token_a_amount, token_b_amount)
+        (token_a_amount.as_u128(), token_b_amount.as_u128())
     }
 
     /// Constant Product Formula (x * y = k)
@@ -127,8 +132,18 @@ impl Math {
 
     /// Calculate square root using Newton's method
     pub fn sqrt(x: Balance) -> Balance {
-        if x == 0 {
-            return 0;
+        Math::sqrt_u256(U256::from(x)).as_u128()
+    }
+
+    /// Integer square root of `a * b`, taken over the U256 product so
+    /// amounts at 1e24 scale don't overflow
+    pub fn sqrt_product(a: Balance, b: Balance) -> Balance {
+        Math::sqrt_u256(U256::from(a) * U256::from(b)).as_u128()
+    }
+
+    fn sqrt_u256(x: U256) -> U256 {
+        if x.is_zero() {
+            return x;
         }
 
         let mut z = x;
@@ -156,15 +171,19 @@ impl Math {
         amount * percentage as u128 / 100
     }
     
+    /// Smallest acceptable output for an expected amount and slippage
+    pub fn min_amount_out(expected: Balance, slippage_bps: u32) -> Balance {
+        let temp = U256::from(expected) * U256::from(Math::FEE_DENOMINATOR - slippage_bps as u128);
+        (temp / U256::from(Math::FEE_DENOMINATOR)).as_u128()
+    }
+
     /// Slippage check
     pub fn check_slippage(
         expected: Balance,
         actual: Balance,
         slippage_bps: u32
     ) -> bool {
-        let min_amount = expected * (Math::FEE_DENOMINATOR - slippage_bps as u128) 
-            / Math::FEE_DENOMINATOR;
-        actual >= min_amount
+        actual >= Math::min_amount_out(expected, slippage_bps)
     }
 }
 
@@ -263,4 +282,39 @@ mod tests {
             "Invalid slippage check failed"
         );
     }
+
+    #[test]
+    fn test_liquidity_tokens_at_near_scale() {
+        let one_near: Balance = 1_000_000_000_000_000_000_000_000;
+
+        // Initial provision is the exact integer sqrt
+        assert_eq!(Math::calculate_liquidity_tokens(3 * one_near, 3 * one_near, 0, 0, 0), 3 * one_near);
+        assert_eq!(Math::sqrt_product(1_000 * one_near, 10 * one_near), 100 * one_near);
+
+        // amount * total_supply is ~1e54 here and overflows u128
+        let reserve = 1_000 * one_near;
+        assert_eq!(
+            Math::calculate_liquidity_tokens(100 * one_near, 100 * one_near, reserve, reserve, reserve),
+            100 * one_near
+        );
+    }
+
+    #[test]
+    fn test_remove_liquidity_at_near_scale() {
+        let one_near: Balance = 1_000_000_000_000_000_000_000_000;
+        let reserve = 1_000 * one_near;
+
+        // lp_tokens * reserve is ~1e53 here
+        assert_eq!(
+            Math::calculate_remove_liquidity(100 * one_near, reserve, reserve, 2 * reserve),
+            (100 * one_near, 200 * one_near)
+        );
+        assert_eq!(Math::calculate_remove_liquidity(0, 0, 0, 0), (0, 0));
+
+        // Swap math at the same scale stays in range too
+        let out = Math::calculate_optimal_swap(100 * one_near, reserve, reserve);
+        assert!(out > 90 * one_near && out < 100 * one_near);
+        let impact = Math::calculate_price_impact(100 * one_near, reserve, reserve);
+        assert!(impact > 0.0 && impact < 20.0);
+    }
 }
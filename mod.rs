@@ -5,7 +5,7 @@ pub mod state;
 
 pub type TokenId = u64;
 
-pub use token::{Token, TokenMetadata, TokenStatus, TokenView};
-pub use block::{Block, BlockView, BlockPhase};
-pub use pool::Pool;
-pub use state::{VoteInfo, StakeInfo};
\ No newline at end of file
+pub use token::{FungibleTokenMetadata, OldToken, OldTokenMetadata, SortKey, SupplyInfo, SupplySchedule, SupplyScheduleView, Token, TokenMetadata, TokenStatus, TokenView, DEFAULT_FT_DECIMALS, DEFAULT_WINNER_SUPPLY, MAX_SYMBOL_LENGTH};
+pub use block::{Block, OldBlock, BlockView, BlockPhase, BlockPhaseConfig, BlockSummaryView, PhaseUpdateResult, QueueOrder, TieBreak, VotingMode, phase_name};
+pub use pool::{Pool, TradeRecord};
+pub use state::{VoteInfo, OldVoteInfo, StakeInfo, Distribution, Cancellation, PendingRefund, PositionEntry, PositionKind, PaymentConfig, PauseFlags, Role};
\ No newline at end of file
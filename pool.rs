@@ -105,7 +105,7 @@ impl Pool {
             token_amount
         } else {
             // Calculate proportional share based on the ratio of new tokens to existing tokens
-            (token_amount * self.token_reserve) / self.token_reserve
+            Math::calculate_share(token_amount, self.token_reserve, self.token_reserve)
         }
     }
 
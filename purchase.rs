@@ -1,74 +1,231 @@
 // actions/purchase.rs
 
-use near_sdk::{env, near_bindgen, AccountId, Balance, Promise};
+use near_sdk::{env, near_bindgen, AccountId, Balance, Gas, Promise, PromiseOrValue, PromiseResult};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
 use crate::*;
 
+const GAS_FOR_PRICE_ORACLE: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_PRICED_CALLBACK: Gas = Gas(50_000_000_000_000);
+
+// Decimals a pool's `usdc_reserve` is kept in, whatever the reference
+// stablecoin's own decimals
+pub const STABLECOIN_DECIMALS: u8 = 6;
+
+// `msg` of an `ft_transfer_call` paying for a purchase
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PurchaseMsg {
+    pub token_id: TokenId,
+    pub amount: U128,
+    #[serde(default)]
+    pub allow_partial: bool,
+}
+
 #[near_bindgen]
 impl TokenBlocks {
+    /// Buys `amount` tokens, or with `allow_partial` as many of them as are
+    /// still available. Returns the amount filled; unused payment is refunded.
     #[payable]
     pub fn purchase_with_native(
         &mut self,
         token_id: TokenId,
-        amount: U128
+        amount: U128,
+        allow_partial: bool
     ) -> Balance {
         let payment = env::attached_deposit();
         let buyer = env::predecessor_account_id();
         
-        self.process_purchase(token_id, amount.0, buyer, Some(payment), None)
+        self.process_purchase(token_id, amount.0, allow_partial, buyer, Some(payment), None).0
     }
 
-    #[payable]
-    pub fn purchase_with_usdc(
+    // Purchase paid with an accepted fungible token, called from
+    // `ft_on_transfer` once the payment has arrived. Tokens with a price
+    // oracle are priced in `on_ft_purchase_priced` once it answers.
+    // Returns the unused part of the payment.
+    pub(crate) fn purchase_with_ft(
         &mut self,
-        token_id: TokenId,
-        amount: U128,
-        usdc_amount: U128
-    ) -> Balance {
-        let buyer = env::predecessor_account_id();
-        
-        // USDC transfer would be handled via ft_transfer_call
-        self.process_purchase(token_id, amount.0, buyer, None, Some(usdc_amount.0))
+        payment_token: AccountId,
+        buyer: AccountId,
+        purchase: PurchaseMsg,
+        payment: Balance
+    ) -> PromiseOrValue<U128> {
+        let config = self.accepted_payment_tokens.get(&payment_token)
+            .expect("Payment token not accepted");
+        match config.price_oracle {
+            Some(oracle) => PromiseOrValue::Promise(Promise::new(oracle)
+                .function_call(
+                    "get_native_price".to_string(),
+                    json!({ "asset": payment_token }).to_string().into_bytes(),
+                    0,
+                    GAS_FOR_PRICE_ORACLE,
+                )
+                .then(Promise::new(env::current_account_id()).function_call(
+                    "on_ft_purchase_priced".to_string(),
+                    json!({
+                        "payment_token": payment_token,
+                        "buyer": buyer,
+                        "purchase": purchase,
+                        "payment": U128(payment),
+                    }).to_string().into_bytes(),
+                    0,
+                    GAS_FOR_PRICED_CALLBACK,
+                ))),
+            None => PromiseOrValue::Value(self.complete_ft_purchase(payment_token, buyer, purchase, payment, None)),
+        }
+    }
+
+    /// Completes an FT purchase with the price the oracle returned, in
+    /// yoctoNEAR per whole payment token. Returns the unused payment; a
+    /// panic here makes the token contract refund the whole transfer.
+    #[private]
+    pub fn on_ft_purchase_priced(
+        &mut self,
+        payment_token: AccountId,
+        buyer: AccountId,
+        purchase: PurchaseMsg,
+        payment: U128,
+    ) -> U128 {
+        let price: U128 = match env::promise_result(0) {
+            PromiseResult::Successful(result) => near_sdk::serde_json::from_slice(&result)
+                .expect("Invalid oracle price"),
+            _ => env::panic_str("Price oracle failed"),
+        };
+        self.complete_ft_purchase(payment_token, buyer, purchase, payment.0, Some(price.0))
+    }
+
+    fn complete_ft_purchase(
+        &mut self,
+        payment_token: AccountId,
+        buyer: AccountId,
+        purchase: PurchaseMsg,
+        payment: Balance,
+        oracle_price: Option<Balance>,
+    ) -> U128 {
+        let (_, charged) = self.process_purchase(
+            purchase.token_id,
+            purchase.amount.0,
+            purchase.allow_partial,
+            buyer,
+            None,
+            Some((payment_token, payment, oracle_price)),
+        );
+        U128(payment - charged)
+    }
+
+    // Admin function to accept another fungible token as purchase payment.
+    // Only one token, the reference stablecoin, may go without an oracle:
+    // pools keep a single stablecoin reserve to price it against.
+    pub fn add_payment_token(&mut self, token_contract: AccountId, config: PaymentConfig) {
+        self.assert_owner();
+        if config.price_oracle.is_none() {
+            assert!(
+                self.accepted_payment_tokens.iter()
+                    .all(|(account_id, listed)| account_id == token_contract || listed.price_oracle.is_some()),
+                "Another payment token is already priced without an oracle"
+            );
+        }
+        self.accepted_payment_tokens.insert(&token_contract, &config);
+    }
+
+    // Admin function to stop accepting a payment token
+    pub fn remove_payment_token(&mut self, token_contract: AccountId) {
+        self.assert_owner();
+        self.accepted_payment_tokens.remove(&token_contract)
+            .expect("Payment token not accepted");
+    }
+
+    // Admin function to refuse purchases from pools holding less native
+    // than this, where the pool price is unreliable
+    pub fn set_min_pool_liquidity_for_purchase(&mut self, min_native_reserve: U128) {
+        self.assert_owner();
+        self.min_pool_liquidity_for_purchase = min_native_reserve.0;
+    }
+
+    /// Accepted payment tokens, ordered by contract account
+    pub fn get_payment_tokens(&self) -> Vec<(AccountId, PaymentConfig)> {
+        let mut payment_tokens = self.accepted_payment_tokens.to_vec();
+        payment_tokens.sort_by(|a, b| a.0.cmp(&b.0));
+        payment_tokens
     }
 
     fn process_purchase(
         &mut self,
         token_id: TokenId,
         amount: Balance,
+        allow_partial: bool,
         buyer: AccountId,
         native_payment: Option<Balance>,
-        usdc_payment: Option<Balance>,
-    ) -> Balance {
+        ft_payment: Option<(AccountId, Balance, Option<Balance>)>,
+    ) -> (Balance, Balance) {
+        assert!(!self.pause_flags.purchase, "Purchases are paused");
         // Validate purchase phase
-        self.assert_valid_purchase_phase(buyer.clone());
+        let is_priority = self.assert_valid_purchase_phase(buyer.clone());
 
         // Get and validate token
         let mut token = self.tokens.get(&token_id)
             .expect("Token not found");
         assert_eq!(token.status, TokenStatus::Winner, "Token not available for purchase");
 
-        // Check available amount
+        // Check available amount, filling what's left when allowed
         let available = token.available_for_purchase();
-        assert!(amount <= available, "Insufficient tokens available");
+        let amount = if allow_partial {
+            amount.min(available)
+        } else {
+            assert!(amount <= available, "Insufficient tokens available");
+            amount
+        };
+        assert!(amount > 0, "Insufficient tokens available");
 
-        // Process payment and calculate tokens
-        let tokens_to_buyer = if let Some(native_payment) = native_payment {
+        // A thin pool prices every payment method unreliably
+        let pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        assert!(
+            pool.native_reserve >= self.min_pool_liquidity_for_purchase,
+            "Pool liquidity below purchase minimum"
+        );
+
+        // Process payment; the buyer gets the full amount either way
+        let tokens_to_buyer = amount;
+        let charged = if let Some(native_payment) = native_payment {
             self.process_native_payment(token_id, amount, native_payment)
-        } else if let Some(usdc_payment) = usdc_payment {
-            self.process_usdc_payment(token_id, amount, usdc_payment)
+        } else if let Some((ref payment_token, payment, oracle_price)) = ft_payment {
+            self.process_ft_payment(token_id, amount, payment_token, payment, oracle_price)
         } else {
             env::panic_str("Invalid payment method");
         };
+        if is_priority {
+            self.record_priority_purchase_time(token_id, &buyer);
+            if self.stake_weighted_priority {
+                self.record_priority_purchase(token_id, &buyer, tokens_to_buyer);
+            }
+        }
 
         // Update token circulating supply
         token.circulating_supply += tokens_to_buyer;
         self.tokens.insert(&token_id, &token);
 
-        // Update pool if necessary
-        self.update_pool(token_id, tokens_to_buyer, native_payment, usdc_payment);
+        // Update pool if necessary. Only the reference stablecoin backs the
+        // stablecoin reserve; oracle-priced payments leave the pool as is.
+        if native_payment.is_some() {
+            self.update_pool(token_id, tokens_to_buyer, Some(charged), None);
+        } else if let Some((ref payment_token, _, None)) = ft_payment {
+            let decimals = self.accepted_payment_tokens.get(payment_token)
+                .expect("Payment token not accepted")
+                .decimals;
+            let normalized = Math::calculate_share(
+                charged,
+                10u128.pow(decimals as u32),
+                10u128.pow(STABLECOIN_DECIMALS as u32),
+            );
+            self.update_pool(token_id, tokens_to_buyer, None, Some(normalized));
+        }
 
-        tokens_to_buyer
+        (tokens_to_buyer, charged)
     }
 
+    // Charges the pool price of `amount`, refunding the rest of `payment`.
+    // Returns the native kept.
     fn process_native_payment(
         &mut self,
         token_id: TokenId,
@@ -87,18 +244,43 @@ impl TokenBlocks {
                 .transfer(payment - required_payment);
         }
 
-        amount
+        required_payment
     }
 
-    fn process_usdc_payment(
+    // Charges `amount` in the payment token. With an oracle price (native
+    // per whole payment token) the pool's native price is converted;
+    // without one the pool's stablecoin reserve, kept in
+    // `STABLECOIN_DECIMALS`, sets the price. Returns the payment kept; the
+    // rest is handed back to the token contract.
+    fn process_ft_payment(
         &mut self,
         token_id: TokenId,
         amount: Balance,
-        usdc_amount: Balance
+        payment_token: &AccountId,
+        payment: Balance,
+        oracle_price: Option<Balance>,
     ) -> Balance {
-        // Similar to native payment but with USDC
-        // Would need to handle USDC price calculations
-        amount
+        let config = self.accepted_payment_tokens.get(payment_token)
+            .expect("Payment token not accepted");
+        let pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        let required_payment = match oracle_price {
+            Some(price) => {
+                assert!(price > 0, "Invalid oracle price");
+                let native_required = pool.calculate_native_required(amount);
+                Math::calculate_share(native_required, price, 10u128.pow(config.decimals as u32))
+            }
+            None => {
+                assert!(pool.usdc_reserve > 0, "Pool has no stablecoin price");
+                Math::calculate_share(
+                    Math::calculate_share(amount, pool.token_reserve, pool.usdc_reserve),
+                    10u128.pow(STABLECOIN_DECIMALS as u32),
+                    10u128.pow(config.decimals as u32),
+                )
+            }
+        };
+        assert!(payment >= required_payment, "Insufficient payment");
+        required_payment
     }
 
     fn update_pool(
@@ -106,7 +288,7 @@ impl TokenBlocks {
         token_id: TokenId,
         amount: Balance,
         native_payment: Option<Balance>,
-        usdc_payment: Option<Balance>
+        ft_payment: Option<Balance>
     ) {
         let mut pool = self.pools.get(&token_id)
             .expect("Pool not found");
@@ -116,14 +298,15 @@ impl TokenBlocks {
         
         if let Some(native_payment) = native_payment {
             pool.add_liquidity(pool_contribution, native_payment * 5 / 100);
-        } else if let Some(usdc_payment) = usdc_payment {
-            pool.add_usdc_liquidity(pool_contribution, usdc_payment * 5 / 100);
+        } else if let Some(ft_payment) = ft_payment {
+            pool.add_usdc_liquidity(pool_contribution, ft_payment * 5 / 100);
         }
 
-        self.pools.insert(&token_id, &pool);
+        self.save_pool(&pool);
     }
 
-    fn assert_valid_purchase_phase(&self, buyer: AccountId) {
+    // Returns whether the purchase falls in the priority phase
+    fn assert_valid_purchase_phase(&self, buyer: AccountId) -> bool {
         let block = self.current_block.as_ref()
             .expect("No active block");
         
@@ -142,6 +325,74 @@ impl TokenBlocks {
                 "Purchase phase ended"
             );
         }
+        is_priority
+    }
+
+    /// Most of a token the account may buy during the priority phase under
+    /// `stake_weighted_priority`: its share of the token's stakes applied
+    /// to the supply offered for sale
+    pub fn get_priority_allocation(&self, token_id: TokenId, account_id: AccountId) -> U128 {
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        let allocation = self.votes.get(&token_id)
+            .and_then(|vote_info| {
+                let stake = vote_info.voters.get(&account_id)?;
+                let total_stake: Balance = vote_info.voters.values().sum();
+                Some(Math::calculate_share(stake, total_stake, token.total_supply - token.pool_reserve))
+            })
+            .unwrap_or(0);
+        U128(allocation)
+    }
+
+    // Adds to what the buyer bought of the token during priority, keeping
+    // the total within their stake-proportional allocation
+    pub(crate) fn record_priority_purchase(&mut self, token_id: TokenId, buyer: &AccountId, amount: Balance) {
+        let purchased = self.priority_purchases.get(buyer)
+            .and_then(|purchases| purchases.get(&token_id).copied())
+            .unwrap_or(0);
+        assert!(
+            purchased + amount <= self.get_priority_allocation(token_id, buyer.clone()).0,
+            "Exceeds priority allocation"
+        );
+        credit_balance(&mut self.priority_purchases, buyer, token_id, amount);
+    }
+
+    // Allows one priority purchase of a token per account every
+    // `priority_purchase_cooldown_ns`
+    pub(crate) fn record_priority_purchase_time(&mut self, token_id: TokenId, buyer: &AccountId) {
+        if self.priority_purchase_cooldown_ns == 0 {
+            return;
+        }
+        let now = env::block_timestamp();
+        let mut purchases = self.last_priority_purchase.get(buyer).unwrap_or_default();
+        if let Some(&last) = purchases.get(&token_id) {
+            assert!(
+                now >= last + self.priority_purchase_cooldown_ns,
+                "Priority purchase cooldown active"
+            );
+        }
+        purchases.insert(token_id, now);
+        self.last_priority_purchase.insert(buyer, &purchases);
+    }
+
+    // Admin function to space out each account's priority purchases of a
+    // token; 0 disables the cooldown
+    pub fn set_priority_purchase_cooldown(&mut self, cooldown_ns: u64) {
+        self.assert_owner();
+        self.priority_purchase_cooldown_ns = cooldown_ns;
+    }
+
+    // Admin function to cap priority purchases by stake instead of first
+    // come, first served
+    pub fn set_stake_weighted_priority(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.stake_weighted_priority = enabled;
+    }
+
+    /// Whether the account may buy during the priority phase, i.e. it has
+    /// staked on a token
+    pub fn is_priority_eligible(&self, account_id: AccountId) -> bool {
+        self.is_voter(&account_id)
     }
 
     fn is_voter(&self, account_id: &AccountId) -> bool {
@@ -1,29 +1,66 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{AccountId, Balance};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{AccountId, Balance, Timestamp};
 use near_sdk::collections::UnorderedMap;
-use crate::TokenId;
+use crate::{TokenId, VotingMode};
 
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct VoteInfo {
     pub total_votes: Balance,
     pub voters: UnorderedMap<AccountId, Balance>,
+    pub bonus_votes: Balance,  // early-vote bonus included in `total_votes`
+    pub voter_bonus: UnorderedMap<AccountId, Balance>,  // each voter's part of `bonus_votes`
 }
 
 impl VoteInfo {
-    pub fn new() -> Self {
+    pub fn new(token_id: TokenId) -> Self {
+        // Each token's voter map needs its own storage prefix
+        let mut prefix = b"vi".to_vec();
+        prefix.extend(token_id.to_le_bytes());
+        let mut bonus_prefix = b"vb".to_vec();
+        bonus_prefix.extend(token_id.to_le_bytes());
         Self {
             total_votes: 0,
-            voters: UnorderedMap::new(b"v"),
+            voters: UnorderedMap::new(prefix),
+            bonus_votes: 0,
+            voter_bonus: UnorderedMap::new(bonus_prefix),
         }
     }
 
-    pub fn add_vote(&mut self, voter: &AccountId, amount: Balance) {
+    // `voters` keeps raw stakes for refunds; `total_votes` sums voting power
+    // plus `bonus_bps` of the power this vote adds
+    pub fn add_vote(&mut self, voter: &AccountId, amount: Balance, mode: &VotingMode, bonus_bps: u32) {
         let current = self.voters.get(voter).unwrap_or(0);
         self.voters.insert(voter, &(current + amount));
-        self.total_votes += amount;
+        let added = mode.power(current + amount) - mode.power(current);
+        let bonus = added * bonus_bps as Balance / 10_000;
+        self.total_votes += added + bonus;
+        self.bonus_votes += bonus;
+        if bonus > 0 {
+            let earned = self.voter_bonus.get(voter).unwrap_or(0);
+            self.voter_bonus.insert(voter, &(earned + bonus));
+        }
+    }
+
+    // Takes the voter's stake out along with its voting power and any
+    // early-vote bonus it earned; returns the stake
+    pub fn remove_vote(&mut self, voter: &AccountId, mode: &VotingMode) -> Option<Balance> {
+        let amount = self.voters.remove(voter)?;
+        let bonus = self.voter_bonus.remove(voter).unwrap_or(0);
+        self.total_votes -= mode.power(amount) + bonus;
+        self.bonus_votes -= bonus;
+        Some(amount)
     }
 }
 
+// Layout of `VoteInfo` before the early-vote bonus, read by `migrate`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldVoteInfo {
+    pub total_votes: Balance,
+    pub voters: UnorderedMap<AccountId, Balance>,
+}
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct StakeInfo {
     pub account_id: AccountId,
@@ -33,9 +70,12 @@ pub struct StakeInfo {
 
 impl StakeInfo {
     pub fn new(account_id: AccountId) -> Self {
+        // Each account's stake map needs its own storage prefix
+        let mut prefix = b"si".to_vec();
+        prefix.extend(account_id.as_bytes());
         Self {
             account_id,
-            stakes: UnorderedMap::new(b"s"),
+            stakes: UnorderedMap::new(prefix),
             total_staked: 0,
         }
     }
@@ -45,4 +85,118 @@ impl StakeInfo {
         self.stakes.insert(&token_id, &(current + amount));
         self.total_staked += amount;
     }
-}
\ No newline at end of file
+}
+
+// How purchases paid in an accepted fungible token are priced
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PaymentConfig {
+    pub decimals: u8,
+    pub price_oracle: Option<AccountId>,  // None: the one reference stablecoin, priced against the pool's stablecoin reserve
+}
+
+// Refund owed to an account that it has to claim itself
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingRefund {
+    pub amount: U128,
+    pub recorded_at: Timestamp,  // last time an amount was added
+}
+
+// Areas of the contract the owner can pause independently
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseFlags {
+    pub trading: bool,
+    pub voting: bool,
+    pub creation: bool,
+    pub purchase: bool,
+}
+
+// An account's standing on the contract, as reported by `get_role`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    Owner,
+    Admin,
+    User,
+}
+
+// What an `export_positions` entry holds
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PositionKind {
+    TokenBalance,
+    Stake,
+    Lp,
+    PendingRefund,
+}
+
+// One line of an account's portfolio export. `token_id` is None for
+// pending refunds, which aren't tied to a token.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PositionEntry {
+    pub kind: PositionKind,
+    pub token_id: Option<TokenId>,
+    pub amount: U128,
+}
+
+// Progress through settling a finalized block's results
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Distribution {
+    pub results: Vec<(TokenId, bool)>,  // (token, is_winner)
+    pub token_index: u64,
+    pub voter_index: u64,
+    pub reward: Balance,          // voting rewards shared by winning voters
+    pub winning_stake: Balance,   // total stake on the winning tokens
+}
+
+impl Distribution {
+    pub fn new(results: Vec<(TokenId, bool)>, reward: Balance, winning_stake: Balance) -> Self {
+        Self {
+            results,
+            token_index: 0,
+            voter_index: 0,
+            reward,
+            winning_stake,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.token_index as usize >= self.results.len()
+    }
+
+    pub fn current(&self) -> (TokenId, bool) {
+        self.results[self.token_index as usize]
+    }
+
+    pub fn next_token(&mut self) {
+        self.token_index += 1;
+        self.voter_index = 0;
+    }
+}
+
+// Progress through refunding a cancelled block's voters
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Cancellation {
+    pub tokens: Vec<TokenId>,
+    pub token_index: u64,
+    pub refunded: u32,
+    pub total: u32,       // voters across all tokens when cancelling began
+}
+
+impl Cancellation {
+    pub fn new(tokens: Vec<TokenId>, total: u32) -> Self {
+        Self {
+            tokens,
+            token_index: 0,
+            refunded: 0,
+            total,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.token_index as usize >= self.tokens.len()
+    }
+}
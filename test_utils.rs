@@ -60,8 +60,8 @@ impl TestUtils {
         token_id: TokenId,
         expected: Balance
     ) {
-        let balance = contract.get_token_balance(account_id, token_id);
-        assert_eq!(balance, expected, "Incorrect token balance");
+        let balance = contract.get_token_balance(account_id.clone(), token_id);
+        assert_eq!(balance.0, expected, "Incorrect token balance");
     }
 
     pub fn create_test_token(
@@ -82,9 +82,10 @@ impl TestUtils {
             expires_at: None,
             starts_at: None,
             extra: None,
+            symbol: None,
         };
 
-        contract.create_token("ipfs://test".to_string(), metadata)
+        contract.create_token("ipfs://test".to_string(), metadata, None, None)
     }
 
     pub fn advance_time(seconds: u64) {
@@ -17,8 +17,9 @@ mod tests {
             expires_at: None,
             starts_at: None,
             extra: None,
+            symbol: None,
         };
-        assert!(Validation::assert_valid_metadata(&valid_metadata));
+        assert!(Validation::assert_valid_metadata(&valid_metadata, false, false));
 
         // Test stake validation
         assert!(Validation::assert_valid_stake(
@@ -58,7 +59,7 @@ mod tests {
         assert!(Time::is_within_range(current_time, start_time, end_time));
         assert!(Time::assert_valid_time_range(start_time, end_time));
 
-        let block_end = Time::get_block_end_time(current_time);
+        let block_end = Time::get_block_end_time(current_time, BLOCK_DURATION);
         assert_eq!(block_end, current_time + 300_000_000_000);
     }
 
@@ -5,10 +5,8 @@ use near_sdk::{env, Timestamp};
 pub struct Time;
 
 impl Time {
-    // Constants for time calculations (in nanoseconds)
-    pub const BLOCK_DURATION: u64 = 300_000_000_000;      // 5 minutes
-    pub const PRIORITY_DURATION: u64 = 120_000_000_000;   // 2 minutes
-    pub const PUBLIC_DURATION: u64 = 180_000_000_000;     // 3 minutes
+    // Constants for time calculations (in nanoseconds). Phase durations
+    // are not defined here: they come from the block being measured.
     pub const ONE_DAY: u64 = 86_400_000_000_000;         // 24 hours
     
     pub fn assert_valid_time_range(start: Timestamp, end: Timestamp) -> bool {
@@ -24,16 +22,16 @@ impl Time {
         current >= start && current <= end
     }
 
-    pub fn get_block_end_time(start_time: Timestamp) -> Timestamp {
-        start_time + Self::BLOCK_DURATION
+    pub fn get_block_end_time(start_time: Timestamp, block_duration: u64) -> Timestamp {
+        start_time + block_duration
     }
 
-    pub fn get_priority_end_time(block_end: Timestamp) -> Timestamp {
-        block_end + Self::PRIORITY_DURATION
+    pub fn get_priority_end_time(block_end: Timestamp, priority_duration: u64) -> Timestamp {
+        block_end + priority_duration
     }
 
-    pub fn get_public_end_time(block_end: Timestamp) -> Timestamp {
-        block_end + Self::PUBLIC_DURATION
+    pub fn get_public_end_time(block_end: Timestamp, public_duration: u64) -> Timestamp {
+        block_end + public_duration
     }
 
     pub fn is_same_day(time1: Timestamp, time2: Timestamp) -> bool {
@@ -63,13 +61,13 @@ mod tests {
     #[test]
     fn test_block_timings() {
         let start_time = 1_000_000;
-        let block_end = Time::get_block_end_time(start_time);
-        let priority_end = Time::get_priority_end_time(block_end);
-        let public_end = Time::get_public_end_time(block_end);
+        let block_end = Time::get_block_end_time(start_time, 300);
+        let priority_end = Time::get_priority_end_time(block_end, 120);
+        let public_end = Time::get_public_end_time(block_end, 180);
         
-        assert_eq!(block_end - start_time, Time::BLOCK_DURATION);
-        assert_eq!(priority_end - block_end, Time::PRIORITY_DURATION);
-        assert_eq!(public_end - block_end, Time::PUBLIC_DURATION);
+        assert_eq!(block_end - start_time, 300);
+        assert_eq!(priority_end - block_end, 120);
+        assert_eq!(public_end - block_end, 180);
     }
 
     #[test]
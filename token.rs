@@ -4,6 +4,13 @@ use near_sdk::{env, AccountId, Balance};
 use near_sdk::json_types::U128;
 use crate::models::TokenId;
 
+// Supply minted for a winner when no schedule says otherwise
+pub const DEFAULT_WINNER_SUPPLY: Balance = 1_000_000;
+
+pub const FT_METADATA_SPEC: &str = "ft-1.0.0";
+pub const DEFAULT_FT_DECIMALS: u8 = 24;
+pub const MAX_SYMBOL_LENGTH: usize = 8;
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub enum TokenStatus {
@@ -12,6 +19,30 @@ pub enum TokenStatus {
     Winner,
     Lost,
     Trading,
+    Removed,
+}
+
+// How a winning token's total supply is determined
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SupplySchedule {
+    Fixed(Balance),
+    FromCopies,                               // metadata.copies
+    Linear { base: Balance, step: Balance },  // base + step per voter
+}
+
+impl SupplySchedule {
+    pub fn total_supply(&self, copies: Option<u64>, voter_count: u64) -> Balance {
+        match self {
+            SupplySchedule::Fixed(amount) => *amount,
+            SupplySchedule::FromCopies => copies
+                .map(Balance::from)
+                .unwrap_or(DEFAULT_WINNER_SUPPLY),
+            SupplySchedule::Linear { base, step } => {
+                base.saturating_add(step.saturating_mul(voter_count as Balance))
+            }
+        }
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -26,6 +57,44 @@ pub struct Token {
     pub pool_reserve: Balance,     // 20% of total supply
     pub status: TokenStatus,
     pub metadata: TokenMetadata,
+    pub supply_schedule: SupplySchedule,
+    pub creator_fee_bps: u32,      // creator's cut of each swap, in bps of the trade
+    pub compound_creator_fee: bool, // reinvest the cut as pool liquidity
+    pub platform_fee_paid: Balance, // forfeited if the token is flagged as spam
+}
+
+// Token record as stored before versioning, converted by `migrate`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldToken {
+    pub id: TokenId,
+    pub creator: AccountId,
+    pub content_hash: String,
+    pub created_at: u64,
+    pub total_supply: Balance,
+    pub circulating_supply: Balance,
+    pub pool_reserve: Balance,
+    pub status: TokenStatus,
+    pub metadata: OldTokenMetadata,
+}
+
+impl From<OldToken> for Token {
+    fn from(old: OldToken) -> Self {
+        Self {
+            id: old.id,
+            creator: old.creator,
+            content_hash: old.content_hash,
+            created_at: old.created_at,
+            total_supply: old.total_supply,
+            circulating_supply: old.circulating_supply,
+            pool_reserve: old.pool_reserve,
+            status: old.status,
+            metadata: old.metadata.into(),
+            supply_schedule: SupplySchedule::Fixed(DEFAULT_WINNER_SUPPLY),
+            creator_fee_bps: 0,
+            compound_creator_fee: false,
+            platform_fee_paid: 0,  // not recorded before versioning
+        }
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -40,6 +109,52 @@ pub struct TokenMetadata {
     pub expires_at: Option<u64>,   // Optional expiration
     pub starts_at: Option<u64>,    // Optional start time
     pub extra: Option<String>,     // Optional extra metadata
+    #[serde(default)]
+    pub symbol: Option<String>,    // Ticker; derived from the title if unset
+}
+
+// Metadata layout as stored before symbols were added
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldTokenMetadata {
+    pub title: String,
+    pub description: Option<String>,
+    pub media: Option<String>,
+    pub media_hash: Option<String>,
+    pub copies: Option<u64>,
+    pub issued_at: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub starts_at: Option<u64>,
+    pub extra: Option<String>,
+}
+
+impl From<OldTokenMetadata> for TokenMetadata {
+    fn from(old: OldTokenMetadata) -> Self {
+        Self {
+            title: old.title,
+            description: old.description,
+            media: old.media,
+            media_hash: old.media_hash,
+            copies: old.copies,
+            issued_at: old.issued_at,
+            expires_at: old.expires_at,
+            starts_at: old.starts_at,
+            extra: old.extra,
+            symbol: None,
+        }
+    }
+}
+
+// NEP-148 fungible token metadata for a winning token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FungibleTokenMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+    pub icon: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<String>,
+    pub decimals: u8,
 }
 
 
@@ -49,6 +164,7 @@ impl Token {
         creator: AccountId,
         content_hash: String,
         metadata: TokenMetadata,
+        supply_schedule: SupplySchedule,
     ) -> Self {
         Self {
             id,
@@ -60,11 +176,20 @@ impl Token {
             pool_reserve: 0,        // 20% of total when created
             status: TokenStatus::Queued,
             metadata,
+            supply_schedule,
+            creator_fee_bps: 0,
+            compound_creator_fee: false,
+            platform_fee_paid: 0,
         }
     }
 
-    pub fn initialize_supply(&mut self, total_supply: Balance) {
+    // Mints the winner's supply according to its schedule, clamped to
+    // `max_supply`
+    pub fn initialize_supply(&mut self, voter_count: u64, max_supply: Balance) {
         assert_eq!(self.total_supply, 0, "Supply already initialized");
+        let total_supply = self.supply_schedule
+            .total_supply(self.metadata.copies, voter_count)
+            .min(max_supply);
         self.total_supply = total_supply;
         self.pool_reserve = total_supply / 5;  // 20% reserve
     }
@@ -76,6 +201,30 @@ impl Token {
     pub fn available_for_purchase(&self) -> Balance {
         self.total_supply - self.circulating_supply - self.pool_reserve
     }
+
+    // Ticker set by the creator, or the first alphanumerics of the title
+    pub fn symbol(&self) -> String {
+        self.metadata.symbol.clone().unwrap_or_else(|| {
+            self.metadata.title
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .take(MAX_SYMBOL_LENGTH)
+                .collect::<String>()
+                .to_ascii_uppercase()
+        })
+    }
+
+    pub fn ft_metadata(&self, decimals: u8) -> FungibleTokenMetadata {
+        FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: self.metadata.title.clone(),
+            symbol: self.symbol(),
+            icon: None,
+            reference: self.metadata.media.clone(),
+            reference_hash: self.metadata.media_hash.clone(),
+            decimals,
+        }
+    }
 }
 
 // View structure for frontend
@@ -93,6 +242,35 @@ pub struct TokenView {
     pub metadata: TokenMetadata,
 }
 
+// Float of a token: how much is in the market vs held in reserve
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SupplyInfo {
+    pub total_supply: U128,
+    pub circulating_supply: U128,
+    pub pool_reserve: U128,
+    pub circulating_ratio: U128,   // scaled by Math::PRECISION
+}
+
+// How a token's supply is or was determined. `total_supply` is only set
+// once the supply has been minted for a winner
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SupplyScheduleView {
+    pub schedule: SupplySchedule,
+    pub total_supply: Option<U128>,
+}
+
+// Orderings offered by `get_tokens_sorted`
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SortKey {
+    CreatedAt,
+    MarketCap,
+    Volume24h,
+    Votes,
+}
+
 impl From<&Token> for TokenView {
     fn from(token: &Token) -> Self {
         Self {
@@ -108,3 +286,74 @@ impl From<&Token> for TokenView {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn winning_token(copies: Option<u64>, supply_schedule: SupplySchedule) -> Token {
+        testing_env!(VMContextBuilder::new().build());
+        let metadata = TokenMetadata {
+            title: "Test Token".to_string(),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            symbol: None,
+        };
+        Token::new(
+            0,
+            AccountId::try_from("creator.near".to_string()).unwrap(),
+            "ipfs://content".to_string(),
+            metadata,
+            supply_schedule,
+        )
+    }
+
+    #[test]
+    fn test_fixed_supply_schedule() {
+        let mut token = winning_token(Some(1000), SupplySchedule::Fixed(5_000));
+        token.initialize_supply(3, Balance::MAX);
+        assert_eq!(token.total_supply, 5_000);
+        assert_eq!(token.pool_reserve, 1_000);
+    }
+
+    #[test]
+    fn test_from_copies_supply_schedule() {
+        let mut token = winning_token(Some(1000), SupplySchedule::FromCopies);
+        token.initialize_supply(3, Balance::MAX);
+        assert_eq!(token.total_supply, 1000);
+
+        let mut token = winning_token(None, SupplySchedule::FromCopies);
+        token.initialize_supply(3, Balance::MAX);
+        assert_eq!(token.total_supply, DEFAULT_WINNER_SUPPLY);
+    }
+
+    #[test]
+    fn test_linear_supply_schedule() {
+        let mut token = winning_token(None, SupplySchedule::Linear { base: 10_000, step: 500 });
+        token.initialize_supply(4, Balance::MAX);
+        assert_eq!(token.total_supply, 12_000);
+    }
+
+    #[test]
+    fn test_supply_clamped_to_max() {
+        let mut token = winning_token(None, SupplySchedule::Fixed(5_000));
+        token.initialize_supply(0, 4_000);
+        assert_eq!(token.total_supply, 4_000);
+        assert_eq!(token.pool_reserve, 800);
+    }
+
+    #[test]
+    fn test_linear_supply_saturates() {
+        let mut token = winning_token(None, SupplySchedule::Linear { base: 1, step: Balance::MAX / 2 });
+        token.initialize_supply(3, 4_000);
+        assert_eq!(token.total_supply, 4_000);
+    }
+}
@@ -3,6 +3,33 @@
 use near_sdk::{env, near_bindgen, AccountId, Balance, Promise};
 use crate::*;
 
+// Number of price points a pool keeps before dropping the oldest
+pub const PRICE_HISTORY_CAPACITY: usize = 100;
+
+// Upper bound for `add_liquidity_tolerance_bps` (1%)
+const MAX_ADD_LIQUIDITY_TOLERANCE_BPS: u32 = 100;
+
+// Fee every new pool charges, in bps of the trade (0.3%)
+pub const DEFAULT_POOL_FEE_RATE: u32 = 30;
+
+// Fee rates a pool may be switched to. None is below the default, so the
+// creator and protocol cuts always fit inside the fee.
+pub const POOL_FEE_TIERS: [u32; 3] = [30, 50, 100];
+
+// Creator and protocol cuts are carved out of the pool fee, so together
+// they must stay within it
+pub const MAX_CREATOR_FEE_BPS: u32 = 10;
+const MAX_PROTOCOL_FEE_BPS: u32 = DEFAULT_POOL_FEE_RATE - MAX_CREATOR_FEE_BPS;
+
+// Swaps kept per account; older ones are dropped first
+pub const TRADE_HISTORY_CAPACITY: usize = 20;
+
+// Dynamic fees look at how far the price ranged over this many of the
+// latest snapshots, adding this many bps per 1% of range, up to the cap
+const VOLATILITY_WINDOW: usize = 10;
+const VOLATILITY_SURCHARGE_PER_PERCENT_BPS: u128 = 5;
+pub const MAX_VOLATILITY_SURCHARGE_BPS: u32 = 100;
+
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct SwapResult {
     pub tokens_out: Balance,
@@ -22,38 +49,54 @@ impl TokenBlocks {
         let buyer = env::predecessor_account_id();
         
         assert!(native_in > 0, "Must attach native tokens");
+        self.assert_tradable(token_id);
         
         let mut pool = self.pools.get(&token_id)
             .expect("Pool not found");
             
         // Calculate swap details
-        let fee_amount = native_in * pool.fee_rate / 10_000; // e.g., 0.3% fee
+        let fee_rate = pool.effective_fee_bps();
+        let fee_amount = native_in * fee_rate / 10_000; // e.g., 0.3% fee
         let native_in_after_fee = native_in - fee_amount;
         
         // Calculate tokens out using constant product formula
         let tokens_out = pool.calculate_tokens_out(native_in_after_fee);
+        assert!(tokens_out > 0, "Output amount is zero");
         assert!(
             tokens_out >= min_tokens_out.0,
             "Slippage tolerance exceeded"
         );
+        self.assert_above_reserve_floor(pool.token_reserve, pool.token_reserve - tokens_out);
         
         // Calculate price impact
         let price_impact = pool.calculate_price_impact(native_in_after_fee, true);
         
-        // Update pool reserves
-        pool.native_reserve += native_in_after_fee;
+        let mut token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        let fee_cuts = self.pay_fee_cuts(&token, &mut pool, native_in, fee_rate, true);
+        
+        // Update pool reserves; the LP part of the fee stays in the pool
+        pool.native_reserve += native_in_after_fee + fee_amount - fee_cuts;
         pool.token_reserve -= tokens_out;
         pool.total_fees += fee_amount;
         pool.update_volume(native_in);
+        pool.update_fees(fee_amount);
+        pool.record_price();
         
         // Update pool state
-        self.pools.insert(&token_id, &pool);
+        self.save_pool(&pool);
         
         // Transfer tokens to buyer
-        let mut token = self.tokens.get(&token_id)
-            .expect("Token not found");
         token.circulating_supply += tokens_out;
         self.tokens.insert(&token_id, &token);
+        credit_balance(&mut self.token_balances, &buyer, token_id, tokens_out);
+        self.record_trade(&buyer, TradeRecord {
+            token_id,
+            is_buy: true,
+            amount_in: U128(native_in),
+            amount_out: U128(tokens_out),
+            timestamp: env::block_timestamp(),
+        });
         
         SwapResult {
             tokens_out,
@@ -71,32 +114,59 @@ impl TokenBlocks {
     ) -> SwapResult {
         let tokens_in = token_amount.0;
         let seller = env::predecessor_account_id();
+        self.assert_tradable(token_id);
+        debit_balance(&mut self.token_balances, &seller, token_id, tokens_in);
         
         let mut pool = self.pools.get(&token_id)
             .expect("Pool not found");
             
         // Calculate swap
-        let fee_amount = tokens_in * pool.fee_rate / 10_000;
+        let fee_rate = pool.effective_fee_bps();
+        let fee_amount = tokens_in * fee_rate / 10_000;
         let tokens_in_after_fee = tokens_in - fee_amount;
         
         // Calculate native out using constant product formula
         let native_out = pool.calculate_native_out(tokens_in_after_fee);
+        assert!(native_out > 0, "Output amount is zero");
         assert!(
             native_out >= min_native_out.0,
             "Slippage tolerance exceeded"
         );
+        self.assert_above_reserve_floor(pool.native_reserve, pool.native_reserve - native_out);
         
         // Calculate price impact
         let price_impact = pool.calculate_price_impact(tokens_in_after_fee, false);
         
-        // Update pool reserves
-        pool.token_reserve += tokens_in_after_fee;
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        let fee_cuts = self.pay_fee_cuts(&token, &mut pool, tokens_in, fee_rate, false);
+        // Only the LP part of the fee is burned; a compounded creator cut
+        // stays in the pool backing the creator's LP
+        let lp_fee = fee_amount
+            - Math::calculate_fee(tokens_in, token.creator_fee_bps)
+            - Math::calculate_fee(tokens_in, self.protocol_fee_bps);
+        let burned = self.burn_fee_tokens(token, lp_fee);
+        
+        // Update pool reserves; the LP part of the fee stays in the pool
+        pool.token_reserve += tokens_in_after_fee + fee_amount - fee_cuts - burned;
         pool.native_reserve -= native_out;
         pool.total_fees += fee_amount;
-        pool.update_volume(tokens_in);
+        // Volume is tracked in native terms, like the fees below
+        pool.update_volume(native_out);
+        // Rolling fees are tracked in native terms
+        pool.update_fees(Math::calculate_share(fee_amount, pool.token_reserve, pool.native_reserve));
+        pool.record_price();
         
         // Update pool state
-        self.pools.insert(&token_id, &pool);
+        self.save_pool(&pool);
+        
+        self.record_trade(&seller, TradeRecord {
+            token_id,
+            is_buy: false,
+            amount_in: U128(tokens_in),
+            amount_out: U128(native_out),
+            timestamp: env::block_timestamp(),
+        });
         
         // Transfer native tokens to seller
         Promise::new(seller).transfer(native_out);
@@ -108,72 +178,495 @@ impl TokenBlocks {
         }
     }
     
+    /// Adds liquidity and returns the id of the LP position minted for it
+    #[payable]
     pub fn add_liquidity(
         &mut self,
         token_id: TokenId,
         token_amount: U128
-    ) -> Balance {
+    ) -> u64 {
         let native_deposit = env::attached_deposit();
         let provider = env::predecessor_account_id();
         
         let mut pool = self.pools.get(&token_id)
             .expect("Pool not found");
             
-        // If first liquidity provision, accept any ratio
-        if pool.native_reserve == 0 {
-            return pool.initialize_liquidity(token_amount.0, native_deposit);
-        }
-        
-        // Calculate optimal amounts
-        let optimal_native = pool.calculate_optimal_native(token_amount.0);
-        assert!(
-            native_deposit >= optimal_native,
-            "Insufficient native tokens"
-        );
-        
-        // Add liquidity
-        let lp_tokens = pool.add_liquidity(token_amount.0, optimal_native);
+        // If first liquidity provision, accept any ratio above the floor
+        let lp_tokens = if pool.native_reserve == 0 {
+            assert!(
+                native_deposit >= self.min_initial_liquidity,
+                "Initial liquidity below minimum"
+            );
+            pool.initialize_liquidity(token_amount.0, native_deposit)
+        } else {
+            // Calculate optimal amounts, allowing deposits a rounding
+            // unit or a few bps short of the optimum
+            let optimal_native = pool.calculate_optimal_native(token_amount.0);
+            let tolerance = if self.add_liquidity_tolerance_bps > 0 {
+                Math::calculate_fee(optimal_native, self.add_liquidity_tolerance_bps).max(1)
+            } else {
+                0
+            };
+            assert!(
+                native_deposit + tolerance >= optimal_native,
+                "Insufficient native tokens"
+            );
+            
+            // Add liquidity; LP tokens follow the smaller side, so a short
+            // deposit is never rewarded with a full share
+            let native_amount = std::cmp::min(native_deposit, optimal_native);
+            let lp_tokens = pool.add_liquidity(token_amount.0, native_amount);
+            
+            // Refund excess native tokens
+            if native_deposit > optimal_native {
+                Promise::new(provider.clone()).transfer(native_deposit - optimal_native);
+            }
+            
+            lp_tokens
+        };
         
-        // Refund excess native tokens
-        if native_deposit > optimal_native {
-            Promise::new(provider).transfer(native_deposit - optimal_native);
+        // Update pool; the tokens come out of the provider's balance
+        self.save_pool(&pool);
+        if token_amount.0 > 0 {
+            debit_balance(&mut self.token_balances, &provider, token_id, token_amount.0);
         }
         
-        // Update pool
-        self.pools.insert(&token_id, &pool);
-        
-        lp_tokens
+        self.mint_lp_position(token_id, lp_tokens, provider)
     }
     
+    /// Burns `lp_tokens` from a position owned by the caller
     pub fn remove_liquidity(
         &mut self,
-        token_id: TokenId,
+        position_id: u64,
         lp_tokens: U128,
         min_native: U128,
         min_tokens: U128
     ) -> (Balance, Balance) {
         let provider = env::predecessor_account_id();
         
+        let mut position = self.lp_positions.get(&position_id)
+            .expect("Position not found");
+        assert_eq!(position.owner, provider, "Not the position owner");
+        assert!(lp_tokens.0 <= position.lp_amount, "Insufficient LP tokens in position");
+        let token_id = position.token_id;
+        
         let mut pool = self.pools.get(&token_id)
             .expect("Pool not found");
             
         // Calculate amounts to return
-        let (native_amount, token_amount) = pool.remove_liquidity(
-            lp_tokens.0,
-            min_native.0,
-            min_tokens.0
-        );
+        let (native_amount, token_amount) = pool.remove_liquidity(lp_tokens.0);
+        assert!(native_amount >= min_native.0, "Native amount below minimum");
+        assert!(token_amount >= min_tokens.0, "Token amount below minimum");
         
         // Update pool state
-        self.pools.insert(&token_id, &pool);
+        self.save_pool(&pool);
         
-        // Transfer assets to provider
+        // Burn against the position
+        position.lp_amount -= lp_tokens.0;
+        if position.lp_amount == 0 {
+            self.lp_positions.remove(&position_id);
+        } else {
+            self.lp_positions.insert(&position_id, &position);
+        }
+        self.debit_lp_balance(&provider, token_id, lp_tokens.0);
+        
+        // Return both assets to the provider
+        credit_balance(&mut self.token_balances, &provider, token_id, token_amount);
         Promise::new(provider).transfer(native_amount);
         
         (native_amount, token_amount)
     }
     
+    pub fn transfer_lp_position(&mut self, position_id: u64, to: AccountId) {
+        let sender = env::predecessor_account_id();
+        let mut position = self.lp_positions.get(&position_id)
+            .expect("Position not found");
+        assert_eq!(position.owner, sender, "Not the position owner");
+        
+        self.debit_lp_balance(&sender, position.token_id, position.lp_amount);
+        self.credit_lp_balance(&to, position.token_id, position.lp_amount);
+        
+        position.owner = to;
+        self.lp_positions.insert(&position_id, &position);
+    }
+    
+    // Admin function to set the native amount a pool must be seeded with
+    pub fn set_min_initial_liquidity(&mut self, min_initial_liquidity: U128) {
+        self.assert_owner();
+        self.min_initial_liquidity = min_initial_liquidity.0;
+    }
+    
+    // Lets the token creator or the owner move a pool to another fee tier.
+    // Swaps read the rate when they run, so only later swaps are affected.
+    pub fn set_pool_fee_rate(&mut self, token_id: TokenId, fee_bps: u32) {
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == token.creator || caller == self.owner_id,
+            "Only the token creator or owner can set the fee"
+        );
+        assert!(POOL_FEE_TIERS.contains(&fee_bps), "Fee rate not an allowed tier");
+        let mut pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        pool.fee_rate = fee_bps;
+        self.save_pool(&pool);
+    }
+    
+    // Lets the token creator reinvest their swap fee cut into the pool as
+    // liquidity instead of taking it out
+    pub fn set_compound_creator_fee(&mut self, token_id: TokenId, enabled: bool) {
+        let mut token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token.creator,
+            "Only the token creator can set fee compounding"
+        );
+        token.compound_creator_fee = enabled;
+        self.tokens.insert(&token_id, &token);
+    }
+    
+    // Admin function to burn part of each token-denominated swap fee
+    pub fn set_fee_burn_bps(&mut self, fee_burn_bps: u32) {
+        self.assert_owner();
+        assert!(fee_burn_bps as u128 <= Math::FEE_DENOMINATOR, "Burn share too high");
+        self.fee_burn_bps = fee_burn_bps;
+    }
+    
+    // Admin function to set the treasury's cut of each swap
+    pub fn set_protocol_fee_bps(&mut self, protocol_fee_bps: u32) {
+        self.assert_owner();
+        assert!(protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS, "Protocol fee too high");
+        self.protocol_fee_bps = protocol_fee_bps;
+    }
+    
+    // Admin function to set the share of a reserve, in bps, a single swap
+    // must leave behind; 0 disables the floor
+    pub fn set_min_reserve_ratio(&mut self, ratio_bps: u32) {
+        self.assert_owner();
+        assert!(ratio_bps as u128 <= Math::FEE_DENOMINATOR, "Ratio too high");
+        self.min_reserve_ratio_bps = ratio_bps;
+    }
+    
+    fn assert_above_reserve_floor(&self, reserve_before: Balance, reserve_after: Balance) {
+        assert!(
+            reserve_after >= Math::calculate_fee(reserve_before, self.min_reserve_ratio_bps),
+            "Swap would drain the reserve below its floor"
+        );
+    }
+    
+    // Admin function to tune how far below optimal a deposit may fall
+    pub fn set_add_liquidity_tolerance(&mut self, tolerance_bps: u32) {
+        self.assert_owner();
+        assert!(
+            tolerance_bps <= MAX_ADD_LIQUIDITY_TOLERANCE_BPS,
+            "Tolerance too high"
+        );
+        self.add_liquidity_tolerance_bps = tolerance_bps;
+    }
+    
+    // Admin function to opt a pool in or out of on-chain price history
+    pub fn set_price_history(&mut self, token_id: TokenId, enabled: bool) {
+        self.assert_owner();
+        let mut pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        pool.price_history_enabled = enabled;
+        if !enabled {
+            // Dynamic fees have nothing left to measure
+            pool.price_snapshots.clear();
+            pool.dynamic_fee_enabled = false;
+        }
+        self.save_pool(&pool);
+    }
+    
+    // Admin function to stop or resume swaps on a single pool
+    pub fn set_trading_enabled(&mut self, token_id: TokenId, enabled: bool) {
+        self.assert_owner();
+        let mut pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        pool.trading_enabled = enabled;
+        self.save_pool(&pool);
+    }
+    
+    // Admin function to surcharge a pool's fee while its price is volatile.
+    // Turns on price history, which the surcharge is measured from.
+    pub fn set_dynamic_fee(&mut self, token_id: TokenId, enabled: bool) {
+        self.assert_owner();
+        let mut pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        pool.dynamic_fee_enabled = enabled;
+        if enabled {
+            pool.price_history_enabled = true;
+        }
+        self.save_pool(&pool);
+    }
+    
+    fn mint_lp_position(&mut self, token_id: TokenId, lp_amount: Balance, owner: AccountId) -> u64 {
+        let position_id = self.position_counter;
+        self.position_counter += 1;
+        
+        self.credit_lp_balance(&owner, token_id, lp_amount);
+        self.lp_positions.insert(&position_id, &LpPosition {
+            token_id,
+            lp_amount,
+            owner,
+        });
+        
+        position_id
+    }
+    
+    // Burns `fee_burn_bps` of the LP part of a token-denominated swap fee.
+    // The fee sits in the pool, not with holders, so only the total supply
+    // shrinks. Returns the amount burned.
+    fn burn_fee_tokens(&mut self, mut token: Token, lp_fee: Balance) -> Balance {
+        let burned = Math::calculate_fee(lp_fee, self.fee_burn_bps)
+            .min(token.total_supply);
+        if burned > 0 {
+            token.total_supply -= burned;
+            self.tokens.insert(&token.id, &token);
+        }
+        burned
+    }
+    
+    // Tops up the creator's compounding position, opening a new one if
+    // they no longer hold it
+    fn credit_creator_lp(&mut self, token: &Token, lp_tokens: Balance) {
+        if lp_tokens == 0 {
+            return;
+        }
+        let existing = self.creator_lp_positions.get(&token.id)
+            .and_then(|position_id| self.lp_positions.get(&position_id).map(|position| (position_id, position)))
+            .filter(|(_, position)| position.owner == token.creator);
+        match existing {
+            Some((position_id, mut position)) => {
+                position.lp_amount += lp_tokens;
+                self.lp_positions.insert(&position_id, &position);
+                self.credit_lp_balance(&token.creator, token.id, lp_tokens);
+            }
+            None => {
+                let position_id = self.mint_lp_position(token.id, lp_tokens, token.creator.clone());
+                self.creator_lp_positions.insert(&token.id, &position_id);
+            }
+        }
+    }
+    
+    fn credit_lp_balance(&mut self, account_id: &AccountId, token_id: TokenId, amount: Balance) {
+        credit_balance(&mut self.lp_balances, account_id, token_id, amount);
+    }
+    
+    fn debit_lp_balance(&mut self, account_id: &AccountId, token_id: TokenId, amount: Balance) {
+        debit_balance(&mut self.lp_balances, account_id, token_id, amount);
+    }
+    
+    // Admin function to open a pool for a winning token that has none yet.
+    // Winners are marked at finalization but only get their supply, pool
+    // reserve and seed from `settle_winner`, so a pool opened before then
+    // would start empty and skip the seed.
+    pub fn create_pool(&mut self, token_id: TokenId) {
+        self.assert_owner();
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        assert_eq!(token.status, TokenStatus::Winner, "Token is not a winner");
+        assert!(token.total_supply > 0, "Winner not settled yet");
+        assert!(self.pools.get(&token_id).is_none(), "Pool already exists");
+        self.ensure_pool(token_id);
+    }
+    
+    // Creates the pool for a token seeded with its pool reserve, unless one
+    // already exists
+    pub(crate) fn ensure_pool(&mut self, token_id: TokenId) {
+        if self.pools.get(&token_id).is_some() {
+            return;
+        }
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        let pool = Pool::new(token_id, token.pool_reserve);
+        self.save_pool(&pool);
+    }
+    
+    // Pays the creator's and protocol's cuts of a swap fee, measured on
+    // `amount_in`, in the asset paid in. Token cuts are credited to the
+    // receiver's balance; native cuts are held for the creator to claim and
+    // sent straight to the treasury. The rest of the fee stays put.
+    fn pay_fee_cuts(
+        &mut self,
+        token: &Token,
+        pool: &mut Pool,
+        amount_in: Balance,
+        fee_rate: u32,
+        is_native: bool
+    ) -> Balance {
+        assert!(
+            token.creator_fee_bps + self.protocol_fee_bps <= fee_rate,
+            "Fee cuts exceed pool fee"
+        );
+        let mut creator_cut = Math::calculate_fee(amount_in, token.creator_fee_bps);
+        let protocol_cut = Math::calculate_fee(amount_in, self.protocol_fee_bps);
+        if token.compound_creator_fee && creator_cut > 0 {
+            // The cut stays in the pool and the creator is credited LP
+            // tokens for it, valued at half the input-side reserve's share
+            let reserve_in = if is_native { pool.native_reserve } else { pool.token_reserve };
+            let lp_tokens = Math::calculate_share(creator_cut, 2 * reserve_in, pool.lp_total_supply);
+            pool.lp_total_supply += lp_tokens;
+            self.credit_creator_lp(token, lp_tokens);
+            creator_cut = 0;
+        }
+        if is_native {
+            if creator_cut > 0 {
+                self.credit_creator(&token.creator, creator_cut);
+            }
+            if protocol_cut > 0 {
+                Promise::new(self.treasury_id.clone()).transfer(protocol_cut);
+            }
+        } else {
+            let treasury_id = self.treasury_id.clone();
+            for (receiver, cut) in [(&token.creator, creator_cut), (&treasury_id, protocol_cut)] {
+                if cut > 0 {
+                    credit_balance(&mut self.token_balances, receiver, token.id, cut);
+                }
+            }
+        }
+        creator_cut + protocol_cut
+    }
+    
+    // Appends to the account's trade history, evicting the oldest entry
+    // once it holds TRADE_HISTORY_CAPACITY records
+    fn record_trade(&mut self, account_id: &AccountId, record: TradeRecord) {
+        let mut history = self.trade_history.get(account_id).unwrap_or_default();
+        if history.len() >= TRADE_HISTORY_CAPACITY {
+            history.remove(0);
+        }
+        history.push(record);
+        self.trade_history.insert(account_id, &history);
+    }
+    
+    // View an account's recent swaps, newest first
+    pub fn get_trade_history(&self, account_id: AccountId) -> Vec<TradeRecord> {
+        let mut history = self.trade_history.get(&account_id).unwrap_or_default();
+        history.reverse();
+        history
+    }
+    
+    // Stores a pool, keeping `total_pool_native` in step with its reserve
+    pub(crate) fn save_pool(&mut self, pool: &Pool) {
+        let previous = self.pools.insert(&pool.token_id, pool)
+            .map(|previous| previous.native_reserve)
+            .unwrap_or(0);
+        self.total_pool_native = self.total_pool_native - previous + pool.native_reserve;
+    }
+    
+    fn assert_tradable(&self, token_id: TokenId) {
+        match self.get_trading_status(token_id) {
+            TradingStatus::Tradable => {}
+            TradingStatus::Paused => env::panic_str("Trading is paused"),
+            TradingStatus::Removed => env::panic_str("Token has been removed"),
+            status => env::panic_str(&format!("Token not tradable: {:?}", status)),
+        }
+    }
+    
+    /// Whether swaps on the token go through right now, or the first
+    /// reason they don't
+    pub fn get_trading_status(&self, token_id: TokenId) -> TradingStatus {
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        if self.pause_flags.trading {
+            return TradingStatus::Paused;
+        }
+        if token.status == TokenStatus::Removed {
+            return TradingStatus::Removed;
+        }
+        if !matches!(token.status, TokenStatus::Winner | TokenStatus::Trading) {
+            return TradingStatus::NotAWinner;
+        }
+        if token.metadata.expires_at.map_or(false, |expires_at| env::block_timestamp() >= expires_at) {
+            return TradingStatus::Expired;
+        }
+        match self.pools.get(&token_id) {
+            Some(pool) if !pool.trading_enabled => TradingStatus::Disabled,
+            Some(pool) if pool.native_reserve > 0 && pool.token_reserve > 0 => TradingStatus::Tradable,
+            _ => TradingStatus::NoPool,
+        }
+    }
+    
     // View methods
+    pub fn get_lp_position(&self, position_id: u64) -> Option<LpPosition> {
+        self.lp_positions.get(&position_id)
+    }
+    
+    pub fn get_lp_positions(
+        &self,
+        account_id: AccountId,
+        from_index: u64,
+        limit: u64
+    ) -> Vec<(TokenId, U128)> {
+        self.lp_balances.get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, balance)| *balance > 0)
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(token_id, balance)| (token_id, U128(balance)))
+            .collect()
+    }
+    
+    /// `(native_amount, token_amount)` the account's LP balance in the
+    /// pool would redeem for at current reserves
+    pub fn get_lp_value(&self, account_id: AccountId, token_id: TokenId) -> (U128, U128) {
+        let pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        let lp_balance = self.lp_balances.get(&account_id)
+            .and_then(|balances| balances.get(&token_id).copied())
+            .unwrap_or(0);
+        if lp_balance == 0 {
+            return (U128(0), U128(0));
+        }
+        let (native_amount, token_amount) = Math::calculate_remove_liquidity(
+            lp_balance,
+            pool.lp_total_supply,
+            pool.native_reserve,
+            pool.token_reserve
+        );
+        (U128(native_amount), U128(token_amount))
+    }
+    
+    /// Raw `(token_reserve, native_reserve, usdc_reserve)` for exact math
+    pub fn get_reserves(&self, token_id: TokenId) -> (U128, U128, U128) {
+        let pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        (
+            U128(pool.token_reserve),
+            U128(pool.native_reserve),
+            U128(pool.usdc_reserve),
+        )
+    }
+    
+    /// Native price of one token, scaled by `Math::PRICE_PRECISION`
+    pub fn get_token_price(&self, token_id: TokenId) -> U128 {
+        let price = self.pools.get(&token_id)
+            .filter(|pool| pool.token_reserve > 0)
+            .map(|pool| pool.get_price_fixed_point())
+            .unwrap_or(0);
+        U128(price)
+    }
+    
+    /// Circulating supply valued at the current pool price, in native
+    pub fn get_market_cap(&self, token_id: TokenId) -> U128 {
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        let price = self.get_token_price(token_id).0;
+        if price == 0 || token.circulating_supply == 0 {
+            return U128(0);
+        }
+        U128(Math::calculate_share(token.circulating_supply, Math::PRICE_PRECISION, price))
+    }
+    
+    pub fn get_price_history(&self, token_id: TokenId) -> Vec<(Timestamp, U128)> {
+        self.pools.get(&token_id)
+            .expect("Pool not found")
+            .price_snapshots
+    }
+    
     pub fn get_pool_info(&self, token_id: TokenId) -> PoolInfo {
         let pool = self.pools.get(&token_id)
             .expect("Pool not found");
@@ -188,6 +681,78 @@ impl TokenBlocks {
         }
     }
     
+    /// All-time swap counters for a pool. Volume adds up the native side
+    /// of each swap: what buys paid in and what sells took out.
+    pub fn get_pool_lifetime_stats(&self, token_id: TokenId) -> PoolLifetimeStats {
+        let pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+
+        let average_trade_size = if pool.swap_count == 0 {
+            0
+        } else {
+            pool.total_volume / pool.swap_count as u128
+        };
+        PoolLifetimeStats {
+            swap_count: pool.swap_count,
+            total_volume: pool.total_volume.into(),
+            average_trade_size: average_trade_size.into(),
+        }
+    }
+    
+    /// Annualized LP yield from the last 24h of fees: `fees_24h * 365 / tvl`
+    pub fn get_pool_apr(&self, token_id: TokenId) -> f64 {
+        let pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+
+        let tvl = pool.get_tvl();
+        if tvl == 0 {
+            return 0.0;
+        }
+
+        pool.fees_24h as f64 * 365.0 / tvl as f64
+    }
+
+    pub fn get_pool_fee_rate(&self, token_id: TokenId) -> u32 {
+        self.pools.get(&token_id)
+            .expect("Pool not found")
+            .fee_rate
+    }
+    
+    /// A swap's fee split into its parts, in the asset paid in. The LP
+    /// part is what stays in the pool after the creator and protocol cuts
+    /// and, on sells, the burn. A compounded creator cut also stays in the
+    /// pool, as LP credited to the creator, and is never burned.
+    pub fn get_fee_breakdown(
+        &self,
+        token_id: TokenId,
+        amount_in: U128,
+        is_native: bool
+    ) -> FeeBreakdown {
+        let pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        let token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        let total_fee = pool.estimate_swap(amount_in.0, is_native).fee_amount.0;
+        let creator_fee = Math::calculate_fee(amount_in.0, token.creator_fee_bps);
+        let protocol_fee = Math::calculate_fee(amount_in.0, self.protocol_fee_bps);
+        let compounded = token.compound_creator_fee && creator_fee > 0;
+        // Same split as `burn_fee_tokens`
+        let burned_fee = if is_native {
+            0
+        } else {
+            Math::calculate_fee(total_fee - creator_fee - protocol_fee, self.fee_burn_bps)
+                .min(token.total_supply)
+        };
+        FeeBreakdown {
+            total_fee: U128(total_fee),
+            lp_fee: U128(total_fee.saturating_sub(creator_fee + protocol_fee + burned_fee)),
+            protocol_fee: U128(protocol_fee),
+            creator_fee: U128(creator_fee),
+            burned_fee: U128(burned_fee),
+            creator_fee_compounded: compounded,
+        }
+    }
+    
     pub fn get_swap_estimate(
         &self,
         token_id: TokenId,
@@ -196,23 +761,75 @@ impl TokenBlocks {
     ) -> SwapEstimate {
         let pool = self.pools.get(&token_id)
             .expect("Pool not found");
-            
-        let amount_in = amount_in.0;
-        let fee_amount = amount_in * pool.fee_rate / 10_000;
-        let amount_in_after_fee = amount_in - fee_amount;
+        pool.estimate_swap(amount_in.0, is_native)
+    }
+    
+    /// Percentage price impact of swapping `amount_in`, after the pool fee,
+    /// without executing it. Same figure as `get_swap_estimate` and the
+    /// swap itself report.
+    pub fn get_price_impact(
+        &self,
+        token_id: TokenId,
+        amount_in: U128,
+        is_native: bool
+    ) -> f64 {
+        self.get_swap_estimate(token_id, amount_in, is_native).price_impact
+    }
+    
+    /// The `min_tokens_out`/`min_native_out` to pass for a swap of
+    /// `amount_in` that tolerates `slippage_bps` of movement
+    pub fn get_min_received(
+        &self,
+        token_id: TokenId,
+        amount_in: U128,
+        is_native: bool,
+        slippage_bps: u32
+    ) -> U128 {
+        self.quote_with_protection(token_id, amount_in, is_native, slippage_bps).1
+    }
+    
+    /// `(expected_out, min_out)` for a swap, from one quote so the two
+    /// always agree
+    pub fn quote_with_protection(
+        &self,
+        token_id: TokenId,
+        amount_in: U128,
+        is_native: bool,
+        slippage_bps: u32
+    ) -> (U128, U128) {
+        assert!(slippage_bps as u128 <= Math::FEE_DENOMINATOR, "Slippage too high");
+        let expected = self.get_swap_estimate(token_id, amount_in, is_native).amount_out.0;
+        (U128(expected), U128(Math::min_amount_out(expected, slippage_bps)))
+    }
+    
+    /// Quotes selling `token_in` for `token_out` through native. The fee is
+    /// the sum of both legs in native terms; price impact compounds.
+    pub fn get_token_to_token_estimate(
+        &self,
+        token_in: TokenId,
+        token_out: TokenId,
+        amount_in: U128
+    ) -> SwapEstimate {
+        assert_ne!(token_in, token_out, "Tokens must differ");
+        let pool_in = self.pools.get(&token_in)
+            .expect("Pool not found for token_in");
+        let pool_out = self.pools.get(&token_out)
+            .expect("Pool not found for token_out");
         
-        let amount_out = if is_native {
-            pool.calculate_tokens_out(amount_in_after_fee)
-        } else {
-            pool.calculate_native_out(amount_in_after_fee)
-        };
+        let first = pool_in.estimate_swap(amount_in.0, false);
+        let second = pool_out.estimate_swap(first.amount_out.0, true);
         
-        let price_impact = pool.calculate_price_impact(amount_in_after_fee, is_native);
+        let first_fee_native = Math::calculate_share(
+            first.fee_amount.0,
+            pool_in.token_reserve,
+            pool_in.native_reserve
+        );
         
         SwapEstimate {
-            amount_out: amount_out.into(),
-            fee_amount: fee_amount.into(),
-            price_impact,
+            amount_out: second.amount_out,
+            fee_amount: (first_fee_native + second.fee_amount.0).into(),
+            price_impact: first.price_impact + second.price_impact
+                - first.price_impact * second.price_impact / 100.0,
         }
     }
 }
@@ -233,6 +850,35 @@ pub struct Pool {
     pub last_updated: Timestamp,
     pub volume_24h: Balance,
     pub last_volume_update: Timestamp,
+    pub fees_24h: Balance,          // native-denominated
+    pub last_fee_update: Timestamp,
+    pub lp_total_supply: Balance,
+    pub price_history_enabled: bool,
+    pub price_snapshots: Vec<(Timestamp, U128)>,  // price scaled by Math::PRICE_PRECISION
+    pub usdc_reserve: Balance,      // reference stablecoin contributions, in STABLECOIN_DECIMALS
+    pub dynamic_fee_enabled: bool,  // surcharge `fee_rate` by recent volatility
+    pub swap_count: u64,
+    pub trading_enabled: bool,      // owner switch for swaps on this pool
+}
+
+// Transferable claim on a share of a pool's liquidity
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LpPosition {
+    pub token_id: TokenId,
+    pub lp_amount: Balance,
+    pub owner: AccountId,
+}
+
+// One swap, as recorded in the trader's history
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TradeRecord {
+    pub token_id: TokenId,
+    pub is_buy: bool,               // native in, tokens out
+    pub amount_in: U128,
+    pub amount_out: U128,
+    pub timestamp: Timestamp,
 }
 
 impl Pool {
@@ -243,10 +889,19 @@ impl Pool {
             native_reserve: 0,
             total_volume: 0,
             total_fees: 0,
-            fee_rate: 30, // 0.3%
+            fee_rate: DEFAULT_POOL_FEE_RATE,
             last_updated: env::block_timestamp(),
             volume_24h: 0,
             last_volume_update: env::block_timestamp(),
+            fees_24h: 0,
+            last_fee_update: env::block_timestamp(),
+            lp_total_supply: 0,
+            price_history_enabled: false,
+            price_snapshots: Vec::new(),
+            usdc_reserve: 0,
+            dynamic_fee_enabled: false,
+            swap_count: 0,
+            trading_enabled: true,
         }
     }
 
@@ -256,31 +911,107 @@ impl Pool {
         native_amount: Balance
     ) -> Balance {
         assert!(self.native_reserve == 0, "Pool already initialized");
-        assert!(token_amount > 0 && native_amount > 0, "Zero amounts");
         
-        self.token_reserve = token_amount;
+        // Pools opened for winners are already seeded with the pool reserve,
+        // so the first provider may pair it with native only
+        let seed_tokens = self.token_reserve;
+        self.token_reserve += token_amount;
+        assert!(self.token_reserve > 0 && native_amount > 0, "Zero amounts");
         self.native_reserve = native_amount;
         self.last_updated = env::block_timestamp();
         
-        // Initial LP tokens are sqrt(x * y)
-        (token_amount as f64 * native_amount as f64).sqrt() as Balance
+        // Initial LP tokens are sqrt(x * y). The seed is worth half its
+        // share of the token side at the opening price; that LP is counted
+        // in the supply but owned by no position, so it stays locked.
+        let lp_tokens = Math::sqrt_product(self.token_reserve, native_amount);
+        self.lp_total_supply = lp_tokens;
+        let seed_lp = Math::calculate_share(seed_tokens, 2 * self.token_reserve, lp_tokens);
+        lp_tokens - seed_lp
+    }
+    
+    pub fn add_liquidity(
+        &mut self,
+        token_amount: Balance,
+        native_amount: Balance
+    ) -> Balance {
+        let lp_tokens = Math::calculate_liquidity_tokens(
+            token_amount,
+            native_amount,
+            self.token_reserve,
+            self.native_reserve,
+            self.lp_total_supply
+        );
+        
+        self.token_reserve += token_amount;
+        self.native_reserve += native_amount;
+        self.lp_total_supply += lp_tokens;
+        self.last_updated = env::block_timestamp();
+        lp_tokens
+    }
+    
+    // Native cost of `token_amount` at the current pool price
+    pub fn calculate_native_required(&self, token_amount: Balance) -> Balance {
+        assert!(self.token_reserve > 0, "Pool has no liquidity");
+        Math::calculate_share(token_amount, self.token_reserve, self.native_reserve)
+    }
+    
+    // Purchase contribution paid in the reference stablecoin; no LP tokens
+    // are minted for it
+    pub fn add_usdc_liquidity(&mut self, token_amount: Balance, usdc_amount: Balance) {
+        self.token_reserve += token_amount;
+        self.usdc_reserve += usdc_amount;
+        self.last_updated = env::block_timestamp();
+    }
+    
+    // Returns (native_amount, token_amount) redeemed for `lp_tokens`
+    pub fn remove_liquidity(&mut self, lp_tokens: Balance) -> (Balance, Balance) {
+        let (token_amount, native_amount) = Math::calculate_remove_liquidity(
+            lp_tokens,
+            self.lp_total_supply,
+            self.token_reserve,
+            self.native_reserve
+        );
+        
+        self.token_reserve -= token_amount;
+        self.native_reserve -= native_amount;
+        self.lp_total_supply -= lp_tokens;
+        self.last_updated = env::block_timestamp();
+        (native_amount, token_amount)
+    }
+    
+    // Native amount that keeps the pool ratio for `token_amount`
+    pub fn calculate_optimal_native(&self, token_amount: Balance) -> Balance {
+        Math::calculate_share(token_amount, self.token_reserve, self.native_reserve)
     }
     
     pub fn calculate_tokens_out(&self, native_in: Balance) -> Balance {
         // x * y = k formula
         // (x + Δx)(y - Δy) = xy
-        // Solving for Δy (tokens_out)
-        let k = self.token_reserve as u128 * self.native_reserve as u128;
-        let new_native_reserve = self.native_reserve as u128 + native_in as u128;
-        let new_token_reserve = k / new_native_reserve;
-        self.token_reserve as u128 - new_token_reserve
+        // Solving for Δy (tokens_out): Δy = y * Δx / (x + Δx), rounded
+        // down so dust inputs can't round up to a free token
+        Math::constant_product(self.native_reserve, self.token_reserve, native_in, 1, 1)
     }
     
     pub fn calculate_native_out(&self, tokens_in: Balance) -> Balance {
-        let k = self.token_reserve as u128 * self.native_reserve as u128;
-        let new_token_reserve = self.token_reserve as u128 + tokens_in as u128;
-        let new_native_reserve = k / new_token_reserve;
-        self.native_reserve as u128 - new_native_reserve
+        Math::constant_product(self.token_reserve, self.native_reserve, tokens_in, 1, 1)
+    }
+    
+    // Output, fee and price impact of a swap, without executing it
+    pub fn estimate_swap(&self, amount_in: Balance, is_native: bool) -> SwapEstimate {
+        let fee_amount = amount_in * self.effective_fee_bps() as Balance / 10_000;
+        let amount_in_after_fee = amount_in - fee_amount;
+        
+        let amount_out = if is_native {
+            self.calculate_tokens_out(amount_in_after_fee)
+        } else {
+            self.calculate_native_out(amount_in_after_fee)
+        };
+        
+        SwapEstimate {
+            amount_out: amount_out.into(),
+            fee_amount: fee_amount.into(),
+            price_impact: self.calculate_price_impact(amount_in_after_fee, is_native),
+        }
     }
     
     pub fn calculate_price_impact(&self, amount_in: Balance, is_native: bool) -> f64 {
@@ -305,6 +1036,7 @@ impl Pool {
     
     pub fn update_volume(&mut self, amount: Balance) {
         self.total_volume += amount;
+        self.swap_count += 1;
         
         let current_time = env::block_timestamp();
         let time_passed = current_time - self.last_volume_update;
@@ -319,6 +1051,64 @@ impl Pool {
         self.last_volume_update = current_time;
     }
     
+    pub fn update_fees(&mut self, fee_amount: Balance) {
+        let current_time = env::block_timestamp();
+        let time_passed = current_time - self.last_fee_update;
+        
+        // Reset 24h fees if more than 24h passed
+        if time_passed >= 24 * 60 * 60 * 1_000_000_000 {
+            self.fees_24h = fee_amount;
+        } else {
+            self.fees_24h += fee_amount;
+        }
+        
+        self.last_fee_update = current_time;
+    }
+    
+    // Total value locked in native terms; the token side is valued at the
+    // spot price, which makes it equal to the native side
+    pub fn get_tvl(&self) -> Balance {
+        self.native_reserve * 2
+    }
+    
+    // Native per token, scaled by Math::PRICE_PRECISION
+    pub fn get_price_fixed_point(&self) -> Balance {
+        Math::calculate_share(Math::PRICE_PRECISION, self.token_reserve, self.native_reserve)
+    }
+    
+    // Fee charged on swaps right now: `fee_rate`, plus in dynamic mode a
+    // surcharge for the price range over the last `VOLATILITY_WINDOW`
+    // snapshots, capped at `MAX_VOLATILITY_SURCHARGE_BPS`
+    pub fn effective_fee_bps(&self) -> u32 {
+        if !self.dynamic_fee_enabled {
+            return self.fee_rate;
+        }
+        self.fee_rate + self.volatility_surcharge_bps()
+    }
+    
+    fn volatility_surcharge_bps(&self) -> u32 {
+        let start = self.price_snapshots.len().saturating_sub(VOLATILITY_WINDOW);
+        let prices = self.price_snapshots[start..].iter().map(|(_, price)| price.0);
+        let (min, max) = match (prices.clone().min(), prices.max()) {
+            (Some(min), Some(max)) if min > 0 => (min, max),
+            _ => return 0,
+        };
+        let range_bps = Math::calculate_share(max - min, min, Math::FEE_DENOMINATOR);
+        (range_bps * VOLATILITY_SURCHARGE_PER_PERCENT_BPS / 100)
+            .min(MAX_VOLATILITY_SURCHARGE_BPS as u128) as u32
+    }
+    
+    // Appends the current price, evicting the oldest point once full
+    pub fn record_price(&mut self) {
+        if !self.price_history_enabled {
+            return;
+        }
+        if self.price_snapshots.len() >= PRICE_HISTORY_CAPACITY {
+            self.price_snapshots.remove(0);
+        }
+        self.price_snapshots.push((env::block_timestamp(), U128(self.get_price_fixed_point())));
+    }
+    
     pub fn get_current_price(&self) -> f64 {
         self.native_reserve as f64 / self.token_reserve as f64
     }
@@ -336,6 +1126,14 @@ pub struct PoolInfo {
     pub price: f64,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolLifetimeStats {
+    pub swap_count: u64,
+    pub total_volume: U128,
+    pub average_trade_size: U128,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct SwapEstimate {
@@ -343,3 +1141,803 @@ pub struct SwapEstimate {
     pub fee_amount: U128,
     pub price_impact: f64,
 }
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeBreakdown {
+    pub total_fee: U128,
+    pub lp_fee: U128,
+    pub protocol_fee: U128,
+    pub creator_fee: U128,
+    pub burned_fee: U128,              // token fees burned on sells
+    pub creator_fee_compounded: bool,  // creator fee kept in the pool as LP
+}
+
+// Why a token can or can't be swapped, from `get_trading_status`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TradingStatus {
+    Tradable,
+    Paused,   // all trading paused through `pause_flags`
+    NotAWinner,
+    Removed,
+    Disabled, // the token's pool has trading switched off
+    Expired,  // past its metadata `expires_at`
+    NoPool,   // no pool, or one without liquidity yet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::json_types::ValidAccountId;
+
+    fn get_context(predecessor: &str, deposit: Balance) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .predecessor_account_id(ValidAccountId::try_from(predecessor.to_string()).unwrap())
+            .current_account_id(ValidAccountId::try_from("contract.near".to_string()).unwrap())
+            .attached_deposit(deposit);
+        builder
+    }
+
+    // Stores a winning token with a pool seeded at the given reserves
+    fn setup_pool(contract: &mut TokenBlocks, token_reserve: Balance, native_reserve: Balance) -> TokenId {
+        let token_id = contract.token_counter;
+        let metadata = TokenMetadata {
+            title: "Test Token".to_string(),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: Some(1000),
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            symbol: None,
+        };
+        let mut token = Token::new(
+            token_id,
+            AccountId::try_from("creator.near".to_string()).unwrap(),
+            "ipfs://content".to_string(),
+            metadata,
+            SupplySchedule::Fixed(DEFAULT_WINNER_SUPPLY),
+        );
+        token.status = TokenStatus::Winner;
+        contract.tokens.insert(&token_id, &token);
+        contract.token_counter += 1;
+
+        let mut pool = Pool::new(token_id, 0);
+        pool.initialize_liquidity(token_reserve, native_reserve);
+        contract.save_pool(&pool);
+        token_id
+    }
+
+    // Gives `account` a ledger balance to provide liquidity or sell from
+    fn fund(contract: &mut TokenBlocks, account: &str, token_id: TokenId, amount: Balance) {
+        credit_balance(&mut contract.token_balances, &account.to_string(), token_id, amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "Output amount is zero")]
+    fn test_dust_swap_reverts() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 10u128.pow(30));
+
+        testing_env!(get_context("buyer.near", 1).build());
+        contract.swap_native_for_tokens(token_id, U128(0));
+    }
+
+    #[test]
+    fn test_pool_apr() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+
+        let mut pool = contract.pools.get(&token_id).unwrap();
+        pool.fees_24h = 2_000;
+        contract.save_pool(&pool);
+
+        // 2_000 * 365 / 2_000_000
+        assert_eq!(contract.get_pool_apr(token_id), 0.365);
+
+        let empty_id = setup_pool(&mut contract, 1_000, 1);
+        let mut empty = contract.pools.get(&empty_id).unwrap();
+        empty.native_reserve = 0;
+        contract.save_pool(&empty);
+        assert_eq!(contract.get_pool_apr(empty_id), 0.0);
+    }
+
+    #[test]
+    fn test_lp_position_transfer_and_remove() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+
+        fund(&mut contract, "alice.near", token_id, 100);
+        testing_env!(get_context("alice.near", 100).build());
+        let position_id = contract.add_liquidity(token_id, U128(100));
+        let position = contract.get_lp_position(position_id).unwrap();
+        assert_eq!(position.lp_amount, 100);
+        assert_eq!(position.owner.to_string(), "alice.near");
+
+        contract.transfer_lp_position(position_id, AccountId::try_from("bob.near".to_string()).unwrap());
+        let bob = AccountId::try_from("bob.near".to_string()).unwrap();
+        assert_eq!(contract.get_lp_position(position_id).unwrap().owner, bob);
+        assert_eq!(contract.lp_balances.get(&bob).unwrap().get(&token_id), Some(&100));
+
+        testing_env!(get_context("bob.near", 0).build());
+        let (native_out, tokens_out) = contract.remove_liquidity(position_id, U128(100), U128(0), U128(0));
+        assert_eq!((native_out, tokens_out), (100, 100));
+        assert!(contract.get_lp_position(position_id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not the position owner")]
+    fn test_remove_liquidity_requires_position_owner() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+
+        fund(&mut contract, "alice.near", token_id, 100);
+        testing_env!(get_context("alice.near", 100).build());
+        let position_id = contract.add_liquidity(token_id, U128(100));
+
+        testing_env!(get_context("bob.near", 0).build());
+        contract.remove_liquidity(position_id, U128(100), U128(0), U128(0));
+    }
+
+    #[test]
+    fn test_add_liquidity_within_tolerance() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+
+        // One unit short of the optimal 100 is accepted, with a matching LP share
+        fund(&mut contract, "alice.near", token_id, 100);
+        testing_env!(get_context("alice.near", 99).build());
+        let position_id = contract.add_liquidity(token_id, U128(100));
+        assert_eq!(contract.get_lp_position(position_id).unwrap().lp_amount, 99);
+        assert_eq!(contract.pools.get(&token_id).unwrap().native_reserve, 1_099);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient native tokens")]
+    fn test_add_liquidity_beyond_tolerance() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+
+        fund(&mut contract, "alice.near", token_id, 100);
+        testing_env!(get_context("alice.near", 98).build());
+        contract.add_liquidity(token_id, U128(100));
+    }
+
+    #[test]
+    fn test_price_history_drops_oldest() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 10u128.pow(12), 10u128.pow(12));
+        contract.set_price_history(token_id, true);
+
+        for i in 0..=PRICE_HISTORY_CAPACITY as u64 {
+            let mut context = get_context("buyer.near", 1_000);
+            context.block_timestamp(i + 1);
+            testing_env!(context.build());
+            contract.swap_native_for_tokens(token_id, U128(0));
+        }
+
+        let history = contract.get_price_history(token_id);
+        assert_eq!(history.len(), PRICE_HISTORY_CAPACITY);
+        // The first swap's point was evicted
+        assert_eq!(history[0].0, 2);
+        assert_eq!(history.last().unwrap().0, PRICE_HISTORY_CAPACITY as u64 + 1);
+        assert!(history.last().unwrap().1 .0 > history[0].1 .0);
+    }
+
+    #[test]
+    fn test_remove_liquidity_returns_both_assets() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 2_000, 1_000);
+
+        fund(&mut contract, "alice.near", token_id, 200);
+        testing_env!(get_context("alice.near", 100).build());
+        let position_id = contract.add_liquidity(token_id, U128(200));
+        let lp_amount = contract.get_lp_position(position_id).unwrap().lp_amount;
+        let lp_supply_before = contract.pools.get(&token_id).unwrap().lp_total_supply;
+
+        testing_env!(get_context("alice.near", 0).build());
+        let (native_out, tokens_out) = contract.remove_liquidity(position_id, U128(lp_amount), U128(0), U128(0));
+
+        // Same 2:1 token/native proportion as the pool
+        assert_eq!(tokens_out, native_out * 2);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        assert_eq!(contract.get_token_balance(alice.clone(), token_id), U128(tokens_out));
+        assert!(contract.lp_balances.get(&alice).is_none());
+        assert_eq!(
+            contract.pools.get(&token_id).unwrap().lp_total_supply,
+            lp_supply_before - lp_amount
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Token amount below minimum")]
+    fn test_remove_liquidity_min_tokens() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+
+        fund(&mut contract, "alice.near", token_id, 100);
+        testing_env!(get_context("alice.near", 100).build());
+        let position_id = contract.add_liquidity(token_id, U128(100));
+
+        testing_env!(get_context("alice.near", 0).build());
+        contract.remove_liquidity(position_id, U128(100), U128(0), U128(101));
+    }
+
+    #[test]
+    fn test_liquidity_round_trip_keeps_balance() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+        fund(&mut contract, "alice.near", token_id, 150);
+
+        testing_env!(get_context("alice.near", 100).build());
+        let position_id = contract.add_liquidity(token_id, U128(100));
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        assert_eq!(contract.get_token_balance(alice.clone(), token_id), U128(50));
+
+        testing_env!(get_context("alice.near", 0).build());
+        contract.remove_liquidity(position_id, U128(100), U128(0), U128(0));
+        assert_eq!(contract.get_token_balance(alice, token_id), U128(150));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient balance")]
+    fn test_add_liquidity_requires_token_balance() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+        fund(&mut contract, "alice.near", token_id, 99);
+
+        testing_env!(get_context("alice.near", 100).build());
+        contract.add_liquidity(token_id, U128(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient balance")]
+    fn test_sell_requires_token_balance() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+
+        testing_env!(get_context("alice.near", 10_000).build());
+        let result = contract.swap_native_for_tokens(token_id, U128(0));
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        assert_eq!(contract.get_token_balance(alice, token_id), U128(result.tokens_out));
+
+        testing_env!(get_context("alice.near", 0).build());
+        contract.swap_tokens_for_native(token_id, U128(result.tokens_out + 1), U128(0));
+    }
+
+    #[test]
+    fn test_min_initial_liquidity() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+        contract.save_pool(&Pool::new(token_id, 0));
+        contract.set_min_initial_liquidity(U128(500));
+
+        fund(&mut contract, "alice.near", token_id, 1_000);
+        testing_env!(get_context("alice.near", 500).build());
+        contract.add_liquidity(token_id, U128(1_000));
+        assert_eq!(contract.pools.get(&token_id).unwrap().native_reserve, 500);
+    }
+
+    #[test]
+    #[should_panic(expected = "Initial liquidity below minimum")]
+    fn test_initial_liquidity_below_minimum() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+        contract.save_pool(&Pool::new(token_id, 0));
+        contract.set_min_initial_liquidity(U128(500));
+
+        fund(&mut contract, "alice.near", token_id, 1_000);
+        testing_env!(get_context("alice.near", 499).build());
+        contract.add_liquidity(token_id, U128(1_000));
+    }
+
+    #[test]
+    fn test_get_lp_positions() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let first = setup_pool(&mut contract, 1_000, 1_000);
+        let second = setup_pool(&mut contract, 1_000, 1_000);
+
+        fund(&mut contract, "alice.near", first, 100);
+        fund(&mut contract, "alice.near", second, 100);
+        testing_env!(get_context("alice.near", 100).build());
+        contract.add_liquidity(first, U128(100));
+        contract.add_liquidity(second, U128(100));
+
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        let positions = contract.get_lp_positions(alice.clone(), 0, 10);
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].0, first);
+        assert_eq!(positions[1].0, second);
+        assert!(positions.iter().all(|(_, balance)| balance.0 > 0));
+
+        assert_eq!(contract.get_lp_positions(alice.clone(), 1, 10), vec![positions[1].clone()]);
+        assert!(contract.get_lp_positions(alice, 2, 10).is_empty());
+    }
+
+    #[test]
+    fn test_market_cap() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 2_000);
+        assert_eq!(contract.get_market_cap(token_id), U128(0));
+
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.circulating_supply = 500;
+        contract.tokens.insert(&token_id, &token);
+
+        assert_eq!(contract.get_token_price(token_id), U128(2 * Math::PRICE_PRECISION));
+        assert_eq!(contract.get_market_cap(token_id), U128(1_000));
+
+        contract.pools.remove(&token_id);
+        assert_eq!(contract.get_market_cap(token_id), U128(0));
+    }
+
+    #[test]
+    fn test_token_to_token_estimate() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_a = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        let token_b = setup_pool(&mut contract, 2_000_000, 1_000_000);
+
+        let estimate = contract.get_token_to_token_estimate(token_a, token_b, U128(10_000));
+
+        let first = contract.get_swap_estimate(token_a, U128(10_000), false);
+        let second = contract.get_swap_estimate(token_b, first.amount_out, true);
+        assert_eq!(estimate.amount_out, second.amount_out);
+        assert_eq!(estimate.fee_amount.0, first.fee_amount.0 + second.fee_amount.0);
+        assert!(estimate.price_impact > first.price_impact);
+        assert!(estimate.price_impact < first.price_impact + second.price_impact);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool not found for token_out")]
+    fn test_token_to_token_estimate_missing_pool() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_a = setup_pool(&mut contract, 1_000_000, 1_000_000);
+
+        contract.get_token_to_token_estimate(token_a, token_a + 1, U128(10_000));
+    }
+
+    #[test]
+    fn test_min_received_matches_swap() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+
+        let expected = contract.get_swap_estimate(token_id, U128(10_000), true).amount_out.0;
+        assert_eq!(
+            contract.get_min_received(token_id, U128(10_000), true, 100),
+            U128(expected * 99 / 100)
+        );
+
+        // Zero slippage puts the minimum exactly at the swap's output
+        let min_out = contract.get_min_received(token_id, U128(10_000), true, 0);
+        testing_env!(get_context("alice.near", 10_000).build());
+        let result = contract.swap_native_for_tokens(token_id, min_out);
+        assert_eq!(result.tokens_out, min_out.0);
+    }
+
+    #[test]
+    fn test_get_reserves_after_swap() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+
+        testing_env!(get_context("alice.near", 10_000).build());
+        contract.swap_native_for_tokens(token_id, U128(0));
+
+        let pool = contract.pools.get(&token_id).unwrap();
+        assert_eq!(
+            contract.get_reserves(token_id),
+            (U128(pool.token_reserve), U128(pool.native_reserve), U128(0))
+        );
+        assert!(pool.token_reserve < 1_000_000);
+    }
+
+    #[test]
+    fn test_creator_fee_on_swaps() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.creator_fee_bps = 10;
+        contract.tokens.insert(&token_id, &token);
+
+        // Buying holds the native cut for the creator to claim
+        testing_env!(get_context("alice.near", 100_000).build());
+        contract.swap_native_for_tokens(token_id, U128(0));
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(contract.get_creator_pending(token.creator.clone()), U128(100));
+
+        // Selling tokens pays the cut in tokens
+        testing_env!(get_context("alice.near", 0).build());
+        contract.swap_tokens_for_native(token_id, U128(50_000), U128(0));
+        assert_eq!(contract.get_token_balance(token.creator, token_id), U128(50));
+    }
+
+    #[test]
+    fn test_trade_history_newest_first_and_bounded() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000_000, 1_000_000_000);
+
+        for i in 0..TRADE_HISTORY_CAPACITY + 2 {
+            testing_env!(get_context("alice.near", 1_000 + i as Balance).build());
+            contract.swap_native_for_tokens(token_id, U128(0));
+        }
+
+        let history = contract.get_trade_history("alice.near".to_string());
+        assert_eq!(history.len(), TRADE_HISTORY_CAPACITY);
+        assert!(history.iter().all(|trade| trade.is_buy && trade.token_id == token_id));
+        // Newest first; the two oldest swaps were evicted
+        assert_eq!(history[0].amount_in, U128(1_000 + TRADE_HISTORY_CAPACITY as Balance + 1));
+        assert_eq!(history[TRADE_HISTORY_CAPACITY - 1].amount_in, U128(1_002));
+        assert!(contract.get_trade_history("bob.near".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_quote_with_protection() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000_000, 1_000_000_000);
+
+        let (expected, min_out) = contract.quote_with_protection(token_id, U128(1_000_000), true, 100);
+        assert!(min_out.0 <= expected.0);
+        assert_eq!(min_out.0, expected.0 * 9_900 / 10_000);
+        assert_eq!(contract.get_min_received(token_id, U128(1_000_000), true, 100), min_out);
+        assert_eq!(contract.get_swap_estimate(token_id, U128(1_000_000), true).amount_out, expected);
+    }
+
+    #[test]
+    fn test_pool_fee_rate_change_applies_to_later_swaps() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000_000, 1_000_000_000);
+        assert_eq!(contract.get_pool_fee_rate(token_id), DEFAULT_POOL_FEE_RATE);
+
+        testing_env!(get_context("alice.near", 100_000).build());
+        assert_eq!(contract.swap_native_for_tokens(token_id, U128(0)).fee_amount, 300);
+
+        testing_env!(get_context("creator.near", 0).build());
+        contract.set_pool_fee_rate(token_id, 100);
+        assert_eq!(contract.get_pool_fee_rate(token_id), 100);
+
+        testing_env!(get_context("alice.near", 100_000).build());
+        assert_eq!(contract.swap_native_for_tokens(token_id, U128(0)).fee_amount, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee rate not an allowed tier")]
+    fn test_pool_fee_rate_outside_tiers() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        contract.set_pool_fee_rate(token_id, 10);
+    }
+
+    #[test]
+    fn test_fee_breakdown_sums_to_total() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000_000, 1_000_000_000);
+        contract.set_protocol_fee_bps(5);
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.creator_fee_bps = 10;
+        contract.tokens.insert(&token_id, &token);
+
+        let breakdown = contract.get_fee_breakdown(token_id, U128(1_000_000), true);
+        assert_eq!(breakdown.total_fee, U128(3_000));
+        assert_eq!(breakdown.creator_fee, U128(1_000));
+        assert_eq!(breakdown.protocol_fee, U128(500));
+        assert_eq!(breakdown.lp_fee, U128(1_500));
+        assert_eq!(
+            breakdown.lp_fee.0 + breakdown.protocol_fee.0 + breakdown.creator_fee.0,
+            contract.get_swap_estimate(token_id, U128(1_000_000), true).fee_amount.0
+        );
+    }
+
+    #[test]
+    fn test_price_impact_matches_estimate_and_swap() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 2_000_000);
+
+        for is_native in [true, false] {
+            assert_eq!(
+                contract.get_price_impact(token_id, U128(25_000), is_native),
+                contract.get_swap_estimate(token_id, U128(25_000), is_native).price_impact
+            );
+        }
+
+        let impact = contract.get_price_impact(token_id, U128(25_000), true);
+        assert!(impact > 0.0);
+        testing_env!(get_context("alice.near", 25_000).build());
+        assert_eq!(contract.swap_native_for_tokens(token_id, U128(0)).price_impact, impact);
+    }
+
+    #[test]
+    fn test_dynamic_fee_rises_with_volatility() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        contract.set_dynamic_fee(token_id, true);
+        assert_eq!(contract.pools.get(&token_id).unwrap().effective_fee_bps(), DEFAULT_POOL_FEE_RATE);
+
+        // Large swaps in both directions swing the price
+        testing_env!(get_context("alice.near", 200_000).build());
+        let result = contract.swap_native_for_tokens(token_id, U128(0));
+        testing_env!(get_context("alice.near", 0).build());
+        contract.swap_tokens_for_native(token_id, U128(result.tokens_out), U128(0));
+
+        let pool = contract.pools.get(&token_id).unwrap();
+        let fee = pool.effective_fee_bps();
+        assert!(fee > DEFAULT_POOL_FEE_RATE);
+        assert!(fee <= DEFAULT_POOL_FEE_RATE + MAX_VOLATILITY_SURCHARGE_BPS);
+        assert_eq!(
+            contract.get_fee_breakdown(token_id, U128(10_000), true).total_fee,
+            U128(10_000 * fee as u128 / 10_000)
+        );
+
+        // Static pools ignore the swings
+        testing_env!(get_context("owner.near", 0).build());
+        contract.set_dynamic_fee(token_id, false);
+        assert_eq!(contract.pools.get(&token_id).unwrap().effective_fee_bps(), DEFAULT_POOL_FEE_RATE);
+    }
+
+    #[test]
+    fn test_reserve_floor_allows_small_swaps() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        contract.set_min_reserve_ratio(5_000);
+
+        testing_env!(get_context("alice.near", 100_000).build());
+        let result = contract.swap_native_for_tokens(token_id, U128(0));
+        contract.swap_tokens_for_native(token_id, U128(result.tokens_out), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Swap would drain the reserve below its floor")]
+    fn test_reserve_floor_rejects_drain() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        contract.set_min_reserve_ratio(5_000);
+
+        // Buying ~99% of the token reserve
+        testing_env!(get_context("alice.near", 100_000_000).build());
+        contract.swap_native_for_tokens(token_id, U128(0));
+    }
+
+    #[test]
+    fn test_lp_value_grows_with_fees() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+
+        fund(&mut contract, "alice.near", token_id, 100_000);
+        testing_env!(get_context("alice.near", 100_000).build());
+        contract.add_liquidity(token_id, U128(100_000));
+        let (native_before, tokens_before) = contract.get_lp_value(alice.clone(), token_id);
+        assert!(native_before.0 > 0 && tokens_before.0 > 0);
+
+        // Round trips leave the token reserve where it was and the fees behind
+        for _ in 0..3 {
+            testing_env!(get_context("bob.near", 50_000).build());
+            let result = contract.swap_native_for_tokens(token_id, U128(0));
+            testing_env!(get_context("bob.near", 0).build());
+            contract.swap_tokens_for_native(token_id, U128(result.tokens_out), U128(0));
+        }
+
+        let (native_after, tokens_after) = contract.get_lp_value(alice, token_id);
+        assert!(native_after.0 > native_before.0);
+        assert!(tokens_after.0 >= tokens_before.0);
+        assert_eq!(contract.get_lp_value("bob.near".to_string(), token_id), (U128(0), U128(0)));
+    }
+
+    #[test]
+    fn test_compounding_creator_fee_credits_lp() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.creator_fee_bps = 10;
+        contract.tokens.insert(&token_id, &token);
+        let creator = token.creator.clone();
+        let lp_before = contract.lp_balances.get(&creator)
+            .and_then(|balances| balances.get(&token_id).copied())
+            .unwrap_or(0);
+
+        testing_env!(get_context(&creator, 0).build());
+        contract.set_compound_creator_fee(token_id, true);
+
+        testing_env!(get_context("alice.near", 100_000).build());
+        contract.swap_native_for_tokens(token_id, U128(0));
+        contract.swap_native_for_tokens(token_id, U128(0));
+
+        assert_eq!(contract.get_creator_pending(creator.clone()), U128(0));
+        let lp_after = contract.lp_balances.get(&creator).unwrap()[&token_id];
+        assert!(lp_after > lp_before);
+        // Both swaps top up the same position
+        let position_id = contract.creator_lp_positions.get(&token_id).unwrap();
+        assert_eq!(contract.get_lp_position(position_id).unwrap().lp_amount, lp_after - lp_before);
+    }
+
+    #[test]
+    fn test_trading_status_reasons() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        assert_eq!(contract.get_trading_status(token_id), TradingStatus::Tradable);
+
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.metadata.expires_at = Some(100);
+        contract.tokens.insert(&token_id, &token);
+        let mut context = get_context("owner.near", 0);
+        context.block_timestamp(100);
+        testing_env!(context.build());
+        assert_eq!(contract.get_trading_status(token_id), TradingStatus::Expired);
+
+        token.metadata.expires_at = None;
+        token.status = TokenStatus::Lost;
+        contract.tokens.insert(&token_id, &token);
+        assert_eq!(contract.get_trading_status(token_id), TradingStatus::NotAWinner);
+
+        token.status = TokenStatus::Removed;
+        contract.tokens.insert(&token_id, &token);
+        assert_eq!(contract.get_trading_status(token_id), TradingStatus::Removed);
+
+        token.status = TokenStatus::Winner;
+        contract.tokens.insert(&token_id, &token);
+        contract.set_trading_enabled(token_id, false);
+        assert_eq!(contract.get_trading_status(token_id), TradingStatus::Disabled);
+        contract.set_trading_enabled(token_id, true);
+
+        contract.set_pause_flags(PauseFlags { trading: true, ..Default::default() });
+        assert_eq!(contract.get_trading_status(token_id), TradingStatus::Paused);
+        contract.set_pause_flags(PauseFlags::default());
+
+        contract.save_pool(&Pool::new(token_id, 0));
+        assert_eq!(contract.get_trading_status(token_id), TradingStatus::NoPool);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token not tradable: Expired")]
+    fn test_swap_rejected_after_expiry() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.metadata.expires_at = Some(100);
+        contract.tokens.insert(&token_id, &token);
+
+        let mut context = get_context("alice.near", 1_000);
+        context.block_timestamp(100);
+        testing_env!(context.build());
+        contract.swap_native_for_tokens(token_id, U128(0));
+    }
+
+    #[test]
+    fn test_fee_burn_reduces_supply() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.total_supply = 2_000_000;
+        token.circulating_supply = 500_000;
+        contract.tokens.insert(&token_id, &token);
+        contract.set_fee_burn_bps(5_000);
+
+        // Each sale pays a 300 token fee, half of which is burned
+        fund(&mut contract, "alice.near", token_id, 300_000);
+        testing_env!(get_context("alice.near", 0).build());
+        for _ in 0..3 {
+            contract.swap_tokens_for_native(token_id, U128(100_000), U128(0));
+        }
+
+        let token = contract.tokens.get(&token_id).unwrap();
+        assert_eq!(token.total_supply, 2_000_000 - 450);
+        // The burned fee came out of the pool, not out of holders' hands
+        assert_eq!(token.circulating_supply, 500_000);
+        assert_eq!(contract.pools.get(&token_id).unwrap().token_reserve, 1_000_000 + 3 * (100_000 - 150));
+    }
+
+    #[test]
+    fn test_fee_breakdown_includes_burn_and_compounding() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000_000, 1_000_000_000);
+        contract.set_fee_burn_bps(5_000);
+        contract.set_protocol_fee_bps(5);
+        let mut token = contract.tokens.get(&token_id).unwrap();
+        token.total_supply = 2_000_000_000;
+        token.creator_fee_bps = 10;
+        contract.tokens.insert(&token_id, &token);
+
+        // Sell: half of what is left after both cuts is burned
+        let breakdown = contract.get_fee_breakdown(token_id, U128(1_000_000), false);
+        assert_eq!(breakdown.burned_fee, U128(750));
+        assert_eq!(breakdown.lp_fee, U128(750));
+        assert!(!breakdown.creator_fee_compounded);
+        // Buys burn nothing
+        assert_eq!(contract.get_fee_breakdown(token_id, U128(1_000_000), true).burned_fee, U128(0));
+
+        // A compounded creator cut stays in the pool, but backs the
+        // creator's LP and is not burned
+        token.compound_creator_fee = true;
+        contract.tokens.insert(&token_id, &token);
+        let breakdown = contract.get_fee_breakdown(token_id, U128(1_000_000), false);
+        assert!(breakdown.creator_fee_compounded);
+        assert_eq!(breakdown.burned_fee, U128(750));
+        assert_eq!(breakdown.lp_fee, U128(750));
+
+        let supply_before = contract.tokens.get(&token_id).unwrap().total_supply;
+        fund(&mut contract, "alice.near", token_id, 1_000_000);
+        testing_env!(get_context("alice.near", 0).build());
+        contract.swap_tokens_for_native(token_id, U128(1_000_000), U128(0));
+        assert_eq!(contract.tokens.get(&token_id).unwrap().total_supply, supply_before - 750);
+    }
+
+    #[test]
+    fn test_pool_lifetime_stats() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000_000, 1_000_000);
+        assert_eq!(contract.get_pool_lifetime_stats(token_id).average_trade_size, U128(0));
+
+        testing_env!(get_context("alice.near", 1_000).build());
+        contract.swap_native_for_tokens(token_id, U128(0));
+        testing_env!(get_context("alice.near", 3_000).build());
+        contract.swap_native_for_tokens(token_id, U128(0));
+        testing_env!(get_context("alice.near", 0).build());
+        let native_out = contract.swap_tokens_for_native(token_id, U128(2_000), U128(0)).tokens_out;
+
+        let total_volume = 1_000 + 3_000 + native_out;
+        assert_eq!(contract.get_pool_lifetime_stats(token_id), PoolLifetimeStats {
+            swap_count: 3,
+            total_volume: U128(total_volume),
+            average_trade_size: U128(total_volume / 3),
+        });
+    }
+
+    #[test]
+    fn test_first_provider_cannot_claim_seed() {
+        testing_env!(get_context("owner.near", 0).build());
+        let mut contract = TokenBlocks::new("owner.near".to_string());
+        let token_id = setup_pool(&mut contract, 1_000, 1_000);
+        contract.save_pool(&Pool::new(token_id, 1_000));
+        contract.set_min_initial_liquidity(U128(0));
+
+        testing_env!(get_context("alice.near", 1_000).build());
+        contract.add_liquidity(token_id, U128(0));
+
+        let pool = contract.pools.get(&token_id).unwrap();
+        let alice = AccountId::try_from("alice.near".to_string()).unwrap();
+        let (native, tokens) = contract.get_lp_value(alice, token_id);
+        assert_eq!(pool.lp_total_supply, 1_000);
+        assert_eq!((native, tokens), (U128(500), U128(500)));
+    }
+}
@@ -7,32 +7,96 @@ pub struct Validation;
 
 impl Validation {
     // Token Metadata Validation
-    pub fn assert_valid_metadata(metadata: &TokenMetadata) -> bool {
+    pub fn assert_valid_metadata(
+        metadata: &TokenMetadata,
+        require_description: bool,
+        require_media: bool
+    ) -> bool {
+        if let Some(problem) = Self::metadata_problems(metadata, require_description, require_media).first() {
+            env::panic_str(problem);
+        }
+        true
+    }
+
+    // Every rule `metadata` breaks, in check order; empty when valid
+    pub fn metadata_problems(
+        metadata: &TokenMetadata,
+        require_description: bool,
+        require_media: bool
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, problem: &str| {
+            if !ok {
+                problems.push(problem.to_string());
+            }
+        };
+
         // Title validation
-        assert!(!metadata.title.is_empty(), "Title cannot be empty");
-        assert!(metadata.title.len() <= 100, "Title too long");
+        check(!metadata.title.is_empty(), "Title cannot be empty");
+        check(metadata.title.len() <= 100, "Title too long");
 
         // Description validation
+        if require_description {
+            check(
+                metadata.description.as_ref().map_or(false, |desc| !desc.trim().is_empty()),
+                "Description is required"
+            );
+        }
         if let Some(ref desc) = metadata.description {
-            assert!(desc.len() <= 1000, "Description too long");
+            check(desc.len() <= 1000, "Description too long");
         }
 
         // Media validation
+        check(!require_media || metadata.media.is_some(), "Media is required");
         if let Some(ref media) = metadata.media {
-            assert!(!media.is_empty(), "Media hash cannot be empty");
-            assert!(
-                media.starts_with("ipfs://") || media.starts_with("ar://"),
-                "Invalid media protocol"
+            check(!media.is_empty(), "Media hash cannot be empty");
+            check(Self::has_storage_protocol(media), "Invalid media protocol");
+
+            // A media hash, when given, must be a real SHA-256 digest
+            if let Some(ref media_hash) = metadata.media_hash {
+                check(Self::verify_media_hash(media_hash.clone()), "Invalid media hash");
+            }
+        }
+
+        // Symbol validation
+        if let Some(ref symbol) = metadata.symbol {
+            check(
+                !symbol.is_empty()
+                    && symbol.len() <= MAX_SYMBOL_LENGTH
+                    && symbol.chars().all(|c| c.is_ascii_alphanumeric()),
+                "Invalid symbol"
             );
         }
 
         // Copies validation
         if let Some(copies) = metadata.copies {
-            assert!(copies > 0, "Copies must be greater than 0");
-            assert!(copies <= 1_000_000_000, "Too many copies");
+            check(copies > 0, "Copies must be greater than 0");
+            check(copies <= 1_000_000_000, "Too many copies");
         }
 
-        true
+        problems
+    }
+
+    // Content Hash Validation
+    pub fn content_hash_problems(content_hash: &str) -> Vec<String> {
+        if content_hash.is_empty() {
+            vec!["Content hash cannot be empty".to_string()]
+        } else if !Self::has_storage_protocol(content_hash) {
+            vec!["Invalid content hash protocol".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn has_storage_protocol(uri: &str) -> bool {
+        uri.starts_with("ipfs://") || uri.starts_with("ar://")
+    }
+
+    // Checks that the hash is base64-encoded and decodes to 32 bytes (SHA-256)
+    pub fn verify_media_hash(provided: String) -> bool {
+        base64::decode(provided)
+            .map(|bytes| bytes.len() == 32)
+            .unwrap_or(false)
     }
 
     // Stake Validation
@@ -130,8 +194,9 @@ mod tests {
             expires_at: None,
             starts_at: None,
             extra: None,
+            symbol: None,
         };
-        assert!(Validation::assert_valid_metadata(&valid_metadata));
+        assert!(Validation::assert_valid_metadata(&valid_metadata, false, false));
     }
 
     #[test]
@@ -147,8 +212,9 @@ mod tests {
             expires_at: None,
             starts_at: None,
             extra: None,
+            symbol: None,
         };
-        Validation::assert_valid_metadata(&invalid_metadata);
+        Validation::assert_valid_metadata(&invalid_metadata, false, false);
     }
 
     #[test]
@@ -161,4 +227,96 @@ mod tests {
     fn test_invalid_stake() {
         Validation::assert_valid_stake(1000, 10, 100);
     }
+
+    #[test]
+    fn test_valid_media_hash() {
+        let metadata = TokenMetadata {
+            title: "Test Token".to_string(),
+            description: None,
+            media: Some("ar://media".to_string()),
+            media_hash: Some(base64::encode([1u8; 32])),
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            symbol: None,
+        };
+        assert!(Validation::assert_valid_metadata(&metadata, false, false));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid media hash")]
+    fn test_wrong_length_media_hash() {
+        let metadata = TokenMetadata {
+            title: "Test Token".to_string(),
+            description: None,
+            media: Some("ar://media".to_string()),
+            media_hash: Some(base64::encode([1u8; 16])),
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            symbol: None,
+        };
+        Validation::assert_valid_metadata(&metadata, false, false);
+    }
+
+    #[test]
+    fn test_media_and_hash_absent() {
+        let metadata = TokenMetadata {
+            title: "Test Token".to_string(),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            symbol: None,
+        };
+        assert!(Validation::assert_valid_metadata(&metadata, false, false));
+        assert!(!Validation::verify_media_hash("not base64!".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid symbol")]
+    fn test_invalid_symbol() {
+        let metadata = TokenMetadata {
+            title: "Test Token".to_string(),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            symbol: Some("TOO-LONG-SYMBOL".to_string()),
+        };
+        Validation::assert_valid_metadata(&metadata, false, false);
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let metadata = TokenMetadata {
+            title: "Test Token".to_string(),
+            description: Some("  ".to_string()),
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            symbol: None,
+        };
+        assert!(Validation::metadata_problems(&metadata, false, false).is_empty());
+        assert_eq!(
+            Validation::metadata_problems(&metadata, true, true),
+            vec!["Description is required".to_string(), "Media is required".to_string()]
+        );
+    }
 }
@@ -1,28 +1,199 @@
 // actions/vote.rs
 
-use near_sdk::{env, near_bindgen, AccountId, Balance, Promise};
+use near_sdk::{env, near_bindgen, AccountId, Balance, Gas, Promise, PromiseOrValue, PromiseResult};
+use near_sdk::serde_json::{json, Value};
 use crate::*;
 
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_IS_VERIFIED: Gas = Gas(5_000_000_000_000);
+// Covers storing a token or a vote once the verifier has answered
+const GAS_FOR_VERIFIED_CALLBACK: Gas = Gas(30_000_000_000_000);
+const GAS_FOR_BLOCK_FINALIZED: Gas = Gas(20_000_000_000_000);
+const GAS_FOR_RESULT_CALLBACK_RESOLVED: Gas = Gas(5_000_000_000_000);
+
 #[near_bindgen]
 impl TokenBlocks {
+    /// Stakes the attached deposit on `token_id`. With an identity verifier
+    /// set, the vote is only recorded once the verifier approves the voter,
+    /// so this returns false and `on_vote_verified` reports the outcome.
     #[payable]
     pub fn vote(&mut self, token_id: TokenId) -> bool {
-        let stake_amount = env::attached_deposit();
+        assert!(
+            self.stake_token.is_none(),
+            "Votes must be staked with ft_transfer_call on the stake token"
+        );
         let voter = env::predecessor_account_id();
+        let stake_amount = env::attached_deposit();
+        if self.identity_verifier.is_some() {
+            self.assert_can_vote(&voter, token_id, stake_amount);
+            self.verify_identity(
+                &voter,
+                "on_vote_verified",
+                json!({ "voter": voter, "token_id": token_id, "amount": U128(stake_amount) }),
+            );
+            return false;
+        }
+        self.internal_vote(voter, token_id, stake_amount)
+    }
 
-        // Validate voting conditions
-        self.assert_active_voting_phase();
-        assert!(stake_amount >= self.min_stake, "Stake too low");
+    /// Records a native vote held back for identity verification, or
+    /// refunds the stake if the verifier rejected the voter or the vote
+    /// stopped being valid while the verifier answered.
+    #[private]
+    pub fn on_vote_verified(&mut self, voter: AccountId, token_id: TokenId, amount: U128) -> bool {
+        if !Self::identity_verified() || self.vote_problem(&voter, token_id, amount.0).is_some() {
+            Promise::new(voter).transfer(amount.0);
+            return false;
+        }
+        self.internal_vote(voter, token_id, amount.0)
+    }
 
-        // Get token and validate
-        let token = self.tokens.get(&token_id)
-            .expect("Token not found");
-        assert_eq!(token.status, TokenStatus::InVoting, "Token not in voting phase");
+    /// Records a stake-token vote held back for identity verification.
+    /// Returns the unused amount, so a rejected or no longer valid stake
+    /// goes back through the token contract's `ft_resolve_transfer`.
+    #[private]
+    pub fn on_ft_vote_verified(&mut self, voter: AccountId, token_id: TokenId, amount: U128) -> U128 {
+        if !Self::identity_verified() || self.vote_problem(&voter, token_id, amount.0).is_some() {
+            return amount;
+        }
+        self.internal_vote(voter, token_id, amount.0);
+        U128(0)
+    }
+
+    /// NEP-141 receiver. A transfer of the stake token is a vote on the
+    /// token id given in `msg`, using the whole amount; a transfer of an
+    /// accepted payment token is a purchase described by a JSON
+    /// `PurchaseMsg`, and whatever the purchase doesn't cost is returned
+    /// as unused.
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token_contract = env::predecessor_account_id();
+        if Some(token_contract.clone()) == self.stake_token {
+            let token_id: TokenId = msg.parse()
+                .expect("Message must be a token id");
+            if self.identity_verifier.is_some() {
+                self.assert_can_vote(&sender_id, token_id, amount.0);
+                return PromiseOrValue::Promise(self.verify_identity(
+                    &sender_id,
+                    "on_ft_vote_verified",
+                    json!({ "voter": sender_id, "token_id": token_id, "amount": amount }),
+                ));
+            }
+            self.internal_vote(sender_id, token_id, amount.0);
+        } else {
+            assert!(
+                self.accepted_payment_tokens.get(&token_contract).is_some(),
+                "Payment token not accepted"
+            );
+            let purchase: PurchaseMsg = near_sdk::serde_json::from_str(&msg)
+                .expect("Message must be a purchase request");
+            return self.purchase_with_ft(token_contract, sender_id, purchase, amount.0);
+        }
+        PromiseOrValue::Value(U128(0))
+    }
+
+    // Admin function to switch staking between native NEAR and a fungible token
+    pub fn set_stake_token(&mut self, stake_token: Option<AccountId>) {
+        self.assert_owner();
+        assert!(self.current_block.is_none(), "Block in progress");
+        self.stake_token = stake_token;
+    }
+
+    // Admin function to gate voting and token creation on an external
+    // `is_verified(account_id)` check; `None` turns the check off
+    pub fn set_identity_verifier(&mut self, verifier: Option<AccountId>) {
+        self.assert_owner();
+        self.identity_verifier = verifier;
+    }
+
+    // Admin function to notify another contract of each block's winners;
+    // `None` turns notifications off
+    pub fn set_result_callback(&mut self, receiver: Option<AccountId>) {
+        self.assert_owner();
+        self.result_callback = receiver;
+    }
+
+    // Admin function to weight early votes up to `bonus_bps` more, falling
+    // linearly to no bonus at the end of voting. Only allowed between
+    // blocks so every vote in a block is weighted the same way.
+    pub fn set_early_vote_bonus(&mut self, bonus_bps: u32) {
+        self.assert_owner();
+        assert!(self.current_block.is_none(), "Block in progress");
+        assert!(bonus_bps as u128 <= Math::FEE_DENOMINATOR, "Bonus too high");
+        self.early_vote_bonus_bps = bonus_bps;
+    }
+
+    // Bonus for a vote cast now: all of `early_vote_bonus_bps` when voting
+    // opens, none when it closes
+    pub(crate) fn current_vote_bonus_bps(&self) -> u32 {
+        let block = match self.current_block.as_ref() {
+            Some(block) if self.early_vote_bonus_bps > 0 => block,
+            _ => return 0,
+        };
+        let (voting_start, voting_end, _) = block.phase_boundaries();
+        let window = voting_end - voting_start;
+        if window == 0 {
+            return 0;
+        }
+        let remaining = voting_end.saturating_sub(env::block_timestamp()).min(window);
+        (self.early_vote_bonus_bps as u128 * remaining as u128 / window as u128) as u32
+    }
+
+    // Admin function to let creators vote on their own tokens
+    pub fn set_allow_self_vote(&mut self, allowed: bool) {
+        self.assert_owner();
+        self.allow_self_vote = allowed;
+    }
+
+    fn assert_can_vote(&self, voter: &AccountId, token_id: TokenId, stake_amount: Balance) {
+        if let Some(problem) = self.vote_problem(voter, token_id, stake_amount) {
+            env::panic_str(problem);
+        }
+    }
+
+    // First reason `voter` can't stake `stake_amount` on `token_id` right
+    // now, if any. Verifier callbacks use it to refund instead of panicking.
+    fn vote_problem(&self, voter: &AccountId, token_id: TokenId, stake_amount: Balance) -> Option<&'static str> {
+        if self.pause_flags.voting {
+            return Some("Voting is paused");
+        }
+        if self.cancelling.is_some() {
+            return Some("Block is being cancelled");
+        }
+        match self.current_block.as_ref() {
+            None => return Some("No active block"),
+            Some(block) if !block.is_voting_phase(env::block_timestamp()) => return Some("Not in voting phase"),
+            _ => {}
+        }
+        if stake_amount < self.min_stake {
+            return Some("Stake too low");
+        }
+
+        let token = match self.tokens.get(&token_id) {
+            Some(token) => token,
+            None => return Some("Token not found"),
+        };
+        if token.status != TokenStatus::InVoting {
+            return Some("Token not in voting phase");
+        }
+        if !self.allow_self_vote && voter == &token.creator {
+            return Some("Creators cannot vote on their own tokens");
+        }
+        None
+    }
+
+    fn internal_vote(&mut self, voter: AccountId, token_id: TokenId, stake_amount: Balance) -> bool {
+        // Validate voting conditions
+        self.assert_can_vote(&voter, token_id, stake_amount);
 
         // Record vote
         let mut vote_info = self.votes.get(&token_id)
-            .unwrap_or_else(|| VoteInfo::new());
-        vote_info.add_vote(&voter, stake_amount);
+            .unwrap_or_else(|| VoteInfo::new(token_id));
+        vote_info.add_vote(&voter, stake_amount, &self.voting_mode, self.current_vote_bonus_bps());
         self.votes.insert(&token_id, &vote_info);
 
         // Record stake
@@ -39,61 +210,913 @@ impl TokenBlocks {
         true
     }
 
+    /// Takes back the caller's whole stake on a token while voting is open.
+    /// `withdraw_penalty_bps` of it goes to the treasury; returns the amount
+    /// refunded.
+    pub fn withdraw_vote(&mut self, token_id: TokenId) -> U128 {
+        assert!(!self.pause_flags.voting, "Voting is paused");
+        assert!(self.cancelling.is_none(), "Block is being cancelled");
+        self.assert_active_voting_phase();
+        let voter = env::predecessor_account_id();
+
+        let mut vote_info = self.votes.get(&token_id)
+            .expect("No votes on this token");
+        let amount = vote_info.remove_vote(&voter, &self.voting_mode)
+            .expect("No vote to withdraw");
+        self.votes.insert(&token_id, &vote_info);
+
+        let mut stake_info = self.stakes.get(&voter)
+            .expect("No stake found");
+        stake_info.stakes.remove(&token_id);
+        stake_info.total_staked -= amount;
+        self.stakes.insert(&voter, &stake_info);
+
+        if let Some(ref mut block) = self.current_block {
+            block.total_stakes -= amount;
+        }
+
+        let penalty = Math::calculate_fee(amount, self.withdraw_penalty_bps);
+        if penalty > 0 {
+            self.transfer_stake(self.treasury_id.clone(), penalty);
+        }
+        self.transfer_stake(voter, amount - penalty);
+        U128(amount - penalty)
+    }
+
+    // Admin function to set the share of a withdrawn vote kept by the treasury
+    pub fn set_withdraw_penalty_bps(&mut self, penalty_bps: u32) {
+        self.assert_owner();
+        assert!(penalty_bps as u128 <= Math::FEE_DENOMINATOR, "Penalty too high");
+        self.withdraw_penalty_bps = penalty_bps;
+    }
+
+    /// Finalizes the block and settles everything in one call. Large blocks
+    /// should use `finalize_voting` followed by batched `distribute` calls.
+    /// A block that ended below `min_participation` gets one grace period
+    /// of extra voting instead.
     pub fn process_voting_results(&mut self) {
+        if self.extend_low_participation_voting() {
+            return;
+        }
+        self.finalize_voting();
+        self.distribute(u32::MAX);
+    }
+
+    // Admin function to re-open voting for `grace_ns` when a block's stakes
+    // end below `min_participation`; a zero threshold turns this off
+    pub fn set_min_participation(&mut self, min_participation: U128, grace_ns: u64) {
+        self.assert_owner();
+        self.min_participation = min_participation.0;
+        self.voting_grace_ns = grace_ns;
+    }
+
+    // Extends voting once for a block that ended below `min_participation`.
+    // Returns whether it did; a second low ending finalizes regardless.
+    fn extend_low_participation_voting(&mut self) -> bool {
+        if self.min_participation == 0 || self.voting_grace_ns == 0 || !self.is_voting_phase_ended() {
+            return false;
+        }
+        let block = self.current_block.as_mut().unwrap();
+        if block.voting_extended || block.total_stakes >= self.min_participation {
+            return false;
+        }
+        block.extend_voting(self.voting_grace_ns, env::block_timestamp());
+        Events::voting_extended(block.start_time, block.voting_end_time);
+        true
+    }
+
+    /// Records winners and losers for the ended block. Supply minting and
+    /// refunds are left to `distribute`. Pays the caller `keeper_reward`.
+    pub fn finalize_voting(&mut self) {
+        if self.extend_low_participation_voting() {
+            return;
+        }
+        self.internal_finalize_voting();
+        self.pay_keeper();
+    }
+
+    pub(crate) fn internal_finalize_voting(&mut self) {
+        assert!(self.cancelling.is_none(), "Block is being cancelled");
         assert!(self.is_voting_phase_ended(), "Voting phase not ended");
+        assert!(
+            self.pending_distribution.is_none(),
+            "Previous block still being distributed"
+        );
         
         // Move the block out of `self.current_block` using `take()`
         let block = self.current_block.take()
             .expect("No active block");
     
         // Now, you can mutably borrow `self` without conflicts
-        let mut token_votes: Vec<(TokenId, Balance)> = block.tokens.iter()
-            .map(|&token_id| {
-                let votes = self.votes.get(&token_id)
-                    .map(|v| v.total_votes)
-                    .unwrap_or(0);
-                (token_id, votes)
-            })
-            .collect();
-    
-        // Sort tokens by the number of votes
-        token_votes.sort_by(|a, b| b.1.cmp(&a.1));
-        let winners: Vec<TokenId> = token_votes.iter()
-            .take(MAX_WINNERS as usize)
-            .map(|(id, _)| *id)
-            .collect();
+        let winners = self.compute_winners(&block);
     
-        // Process each token
+        // Mark each token
+        let mut results = Vec::with_capacity(block.tokens.len());
         for &token_id in &block.tokens {
             let mut token = self.tokens.get(&token_id)
                 .expect("Token not found");
     
-            if winners.contains(&token_id) {
-                token.status = TokenStatus::Winner;
-                token.initialize_supply(1_000_000);
-            } else {
-                token.status = TokenStatus::Lost;
-                self.return_stakes(token_id);
+            // Tokens flagged as spam keep their Removed status
+            let is_winner = winners.contains(&token_id);
+            if token.status != TokenStatus::Removed {
+                token.status = if is_winner {
+                    TokenStatus::Winner
+                } else {
+                    TokenStatus::Lost
+                };
             }
     
             self.tokens.insert(&token_id, &token);
+            if is_winner {
+                self.winners.push(&token_id);
+                let mut wins = self.creator_wins.get(&token.creator).unwrap_or_default();
+                wins.push(token_id);
+                self.creator_wins.insert(&token.creator, &wins);
+            }
+            // Winners get their bond back from `settle_winner`, so one
+            // flagged before then still forfeits it
+            if !is_winner {
+                self.settle_bond(token_id, &token.creator, false);
+            }
+            results.push((token_id, is_winner));
         }
+
+        // Rewards are only claimed when someone staked on a winner. The
+        // early-vote bonus only counts toward ranking, not reward shares.
+        let winning_stake: Balance = winners.iter()
+            .filter_map(|token_id| self.votes.get(token_id))
+            .map(|v| v.total_votes - v.bonus_votes)
+            .sum();
+        let reward = if winning_stake > 0 {
+            std::mem::take(&mut self.voting_reward_pool)
+        } else {
+            0
+        };
+        self.pending_distribution = Some(Distribution::new(results, reward, winning_stake));
+        self.notify_result_callback(winners);
     
         // Optionally, start a new block if there are tokens in the queue
-        if !self.token_queue.is_empty() {
-            self.start_block();
+        self.complete_block();
+    }
+
+    /// Settles up to `limit` items of the finalized block: one per winner
+    /// (supply minting), one per rewarded or refunded voter of a winning
+    /// token and one per refunded voter of a losing token.
+    /// Returns true once everything has been distributed.
+    pub fn distribute(&mut self, limit: u32) -> bool {
+        let mut distribution = self.pending_distribution.take()
+            .expect("Nothing to distribute");
+
+        let mut processed = 0;
+        while processed < limit && !distribution.is_complete() {
+            // Refunds follow the finalized result, not the token's status,
+            // so voters of any non-winning token get their stake back
+            let (token_id, is_winner) = distribution.current();
+            let vote_info = self.votes.get(&token_id);
+            let voter_count = vote_info.as_ref().map(|v| v.voters.len()).unwrap_or(0);
+            let pays_winner_voters = distribution.reward > 0 || self.refund_winner_stakes;
+            if is_winner && (!pays_winner_voters || distribution.voter_index >= voter_count) {
+                self.settle_winner(token_id);
+                distribution.next_token();
+                processed += 1;
+                continue;
+            }
+
+            if distribution.voter_index < voter_count {
+                let vote_info = vote_info.unwrap();
+                let index = distribution.voter_index;
+                let voter = vote_info.voters.keys_as_vector().get(index).unwrap();
+                let amount = vote_info.voters.values_as_vector().get(index).unwrap();
+                if is_winner {
+                    let share = Math::calculate_share(
+                        self.voting_mode.power(amount),
+                        distribution.winning_stake,
+                        distribution.reward
+                    );
+                    if share > 0 {
+                        Promise::new(voter.clone()).transfer(share);
+                    }
+                    if self.refund_winner_stakes {
+                        self.refund_stake(voter, amount);
+                    }
+                } else {
+                    self.refund_stake(voter, amount);
+                }
+                distribution.voter_index += 1;
+                processed += 1;
+            } else {
+                distribution.next_token();
+            }
+        }
+
+        if distribution.is_complete() {
+            true
+        } else {
+            self.pending_distribution = Some(distribution);
+            false
+        }
+    }
+
+    /// Runs one `distribute` batch of `refund_batch_size` items and returns
+    /// how many refunds are still pending
+    pub fn process_refunds(&mut self) -> u32 {
+        self.distribute(self.refund_batch_size);
+        self.get_pending_refund_count()
+    }
+
+    // Adds to an account's claimable refund and restarts its expiry clock
+    pub(crate) fn record_pending_refund(&mut self, account_id: &AccountId, amount: Balance) {
+        let owed = self.pending_refunds.get(account_id)
+            .map(|refund| refund.amount.0)
+            .unwrap_or(0);
+        self.pending_refunds.insert(account_id, &PendingRefund {
+            amount: U128(owed + amount),
+            recorded_at: env::block_timestamp(),
+        });
+        self.total_pending_refunds += amount;
+    }
+
+    // Refunds a losing stake. Amounts below `min_refund_amount` are held in
+    // the voter's pending refund until the total crosses the threshold, then
+    // paid out in a single transfer.
+    pub(crate) fn refund_stake(&mut self, voter: AccountId, amount: Balance) {
+        if amount >= self.min_refund_amount {
+            self.transfer_stake(voter, amount);
+            return;
+        }
+        self.record_pending_refund(&voter, amount);
+        let owed = self.pending_refunds.get(&voter).unwrap().amount.0;
+        if owed >= self.min_refund_amount {
+            self.pending_refunds.remove(&voter);
+            self.total_pending_refunds -= owed;
+            self.transfer_stake(voter, owed);
+        }
+    }
+
+    // Admin function to set the smallest refund worth a transfer
+    pub fn set_min_refund_amount(&mut self, min_refund_amount: U128) {
+        self.assert_owner();
+        self.min_refund_amount = min_refund_amount.0;
+    }
+
+    /// Pays out the caller's pending refund
+    pub fn claim_refund(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let refund = self.pending_refunds.remove(&account_id)
+            .expect("No pending refund");
+        self.total_pending_refunds -= refund.amount.0;
+        self.transfer_stake(account_id, refund.amount.0);
+        refund.amount
+    }
+
+    /// Moves a refund left unclaimed past `refund_expiry_ns` to the treasury
+    pub fn sweep_unclaimed_refunds(&mut self, account_id: AccountId) -> U128 {
+        self.assert_owner();
+        let refund = self.pending_refunds.get(&account_id)
+            .expect("No pending refund");
+        assert!(
+            env::block_timestamp() >= refund.recorded_at + self.refund_expiry_ns,
+            "Refund not expired"
+        );
+        self.pending_refunds.remove(&account_id);
+        self.total_pending_refunds -= refund.amount.0;
+        self.transfer_stake(self.treasury_id.clone(), refund.amount.0);
+        refund.amount
+    }
+
+    // Admin function to set how long refunds stay claimable
+    pub fn set_refund_expiry(&mut self, expiry_ns: u64) {
+        self.assert_owner();
+        self.refund_expiry_ns = expiry_ns;
+    }
+
+    // Admin function to set where forfeited and swept funds go
+    pub fn set_treasury(&mut self, treasury_id: AccountId) {
+        self.assert_owner();
+        self.treasury_id = treasury_id;
+    }
+
+    // Admin function to hide standings for the last `blackout_ns` of voting
+    // so nobody can snipe with exact knowledge of the cutoff (0 = never)
+    pub fn set_standings_blackout(&mut self, blackout_ns: u64) {
+        self.assert_owner();
+        assert!(blackout_ns <= self.phases.voting_duration, "Blackout longer than voting");
+        self.standings_blackout_ns = blackout_ns;
+    }
+
+    // Admin function to return winning stakes to their voters instead of
+    // funding the pool and creator with them
+    pub fn set_refund_winner_stakes(&mut self, refund_winner_stakes: bool) {
+        self.assert_owner();
+        assert!(
+            self.current_block.is_none() && self.pending_distribution.is_none(),
+            "Block in progress"
+        );
+        self.refund_winner_stakes = refund_winner_stakes;
+    }
+
+    // Admin function to set the share of a winner's stakes seeding its pool
+    pub fn set_pool_seed_bps(&mut self, pool_seed_bps: u32) {
+        self.assert_owner();
+        assert!(pool_seed_bps as u128 <= Math::FEE_DENOMINATOR, "Seed share too high");
+        self.pool_seed_bps = pool_seed_bps;
+    }
+
+    // Admin function to size `process_refunds` batches to the gas limit
+    pub fn set_refund_batch_size(&mut self, batch_size: u32) {
+        self.assert_owner();
+        assert!(batch_size > 0, "Batch size must be positive");
+        self.refund_batch_size = batch_size;
+    }
+
+    // Returns a creator's bond if the token won, otherwise forfeits it to
+    // the treasury
+    fn settle_bond(&mut self, token_id: TokenId, creator: &AccountId, is_winner: bool) {
+        if let Some(bond) = self.token_bonds.remove(&token_id) {
+            if is_winner {
+                self.credit_creator(creator, bond);
+            } else {
+                Promise::new(self.treasury_id.clone()).transfer(bond);
+            }
+        }
+    }
+
+    fn settle_winner(&mut self, token_id: TokenId) {
+        let mut token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        // Flagged as spam after finalization
+        if token.status == TokenStatus::Removed {
+            return;
+        }
+        self.settle_bond(token_id, &token.creator, true);
+        let voter_count = self.votes.get(&token_id)
+            .map(|v| v.voters.len())
+            .unwrap_or(0);
+        token.initialize_supply(voter_count, self.max_token_supply);
+        self.tokens.insert(&token_id, &token);
+        self.ensure_pool(token_id);
+        self.allocate_raised_stake(token_id, token.creator);
+    }
+
+    // Whether `settle_winner` has run for the token. Its voters stay on
+    // record for priority allocations, but their stakes are spent or
+    // refunded by then and must not be paid out again.
+    pub(crate) fn is_settled(&self, token_id: TokenId) -> bool {
+        self.pools.get(&token_id).is_some()
+    }
+
+    // Splits a winner's native voter stakes: `pool_seed_bps` seeds the
+    // pool's native side as locked liquidity, the rest funds the creator.
+    // Stakes held in a stake token, or refunded under
+    // `refund_winner_stakes`, are left untouched.
+    fn allocate_raised_stake(&mut self, token_id: TokenId, creator: AccountId) {
+        if self.stake_token.is_some() || self.refund_winner_stakes {
+            return;
+        }
+        let raised: Balance = self.votes.get(&token_id)
+            .map(|v| v.voters.values().sum())
+            .unwrap_or(0);
+        if raised == 0 {
+            return;
+        }
+
+        let mut pool = self.pools.get(&token_id)
+            .expect("Pool not found");
+        let seed = if pool.token_reserve > 0 {
+            Math::calculate_fee(raised, self.pool_seed_bps)
         } else {
-            self.current_block = None;
+            0
+        };
+        if seed > 0 {
+            if pool.native_reserve == 0 {
+                pool.initialize_liquidity(0, seed);
+            } else {
+                pool.native_reserve += seed;
+            }
+            self.save_pool(&pool);
         }
+
+        if raised > seed {
+            self.credit_creator(&creator, raised - seed);
+        }
+    }
+
+    // Owner-funded rewards, shared by the winning voters of the next block
+    #[payable]
+    pub fn fund_rewards(&mut self) {
+        self.assert_owner();
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Deposit required");
+        self.voting_reward_pool += amount;
     }
 
-    fn return_stakes(&mut self, token_id: TokenId) {
-        if let Some(vote_info) = self.votes.get(&token_id) {
-            for (voter, amount) in vote_info.voters.iter() {
-                Promise::new(voter.clone())
-                    .transfer(*amount);
+    /// Cancels the current block, refunding every voter in full. Refunds up
+    /// to `limit` voters per call; voting stays closed and the block stays
+    /// current until the last refund, when its tokens go back to the queue.
+    /// Returns true once the block is gone.
+    pub fn cancel_block(&mut self, limit: u32) -> bool {
+        self.assert_owner();
+        let mut cancellation = self.cancelling.take().unwrap_or_else(|| {
+            let block = self.current_block.as_ref().expect("No active block");
+            let total = block.tokens.iter()
+                .filter_map(|token_id| self.votes.get(token_id))
+                .map(|vote_info| vote_info.voters.len() as u32)
+                .sum();
+            Cancellation::new(block.tokens.clone(), total)
+        });
+
+        let mut processed = 0;
+        while processed < limit && !cancellation.is_complete() {
+            let token_id = cancellation.tokens[cancellation.token_index as usize];
+            let mut vote_info = match self.votes.get(&token_id) {
+                Some(vote_info) if !vote_info.voters.is_empty() => vote_info,
+                _ => {
+                    self.votes.remove(&token_id);
+                    cancellation.token_index += 1;
+                    continue;
+                }
+            };
+
+            // Take voters off the end so the map never has to shuffle
+            let voter = vote_info.voters.keys_as_vector().get(vote_info.voters.len() - 1).unwrap();
+            let amount = vote_info.remove_vote(&voter, &self.voting_mode).unwrap();
+            self.votes.insert(&token_id, &vote_info);
+            if let Some(mut stake_info) = self.stakes.get(&voter) {
+                stake_info.stakes.remove(&token_id);
+                stake_info.total_staked -= amount;
+                if stake_info.stakes.is_empty() {
+                    self.stakes.remove(&voter);
+                } else {
+                    self.stakes.insert(&voter, &stake_info);
+                }
+            }
+            if let Some(ref mut block) = self.current_block {
+                block.total_stakes -= amount;
+            }
+            self.transfer_stake(voter, amount);
+
+            cancellation.refunded += 1;
+            processed += 1;
+        }
+
+        if !cancellation.is_complete() {
+            self.cancelling = Some(cancellation);
+            return false;
+        }
+
+        // Requeue from the live block, which also holds tokens that joined
+        // while accepting, whatever phase it reached; removed and finished
+        // tokens stay out
+        let block = self.current_block.take().expect("No active block");
+        for token_id in block.tokens {
+            if let Some(mut token) = self.tokens.get(&token_id) {
+                if matches!(
+                    token.status,
+                    TokenStatus::Queued | TokenStatus::Pending | TokenStatus::InVoting | TokenStatus::Public
+                ) {
+                    token.status = TokenStatus::Queued;
+                    self.tokens.insert(&token_id, &token);
+                    self.token_queue.push(token_id);
+                }
             }
         }
+        self.last_block_end = Some(env::block_timestamp());
+        true
+    }
+
+    /// `(refunded, total)` voters of a block being cancelled
+    pub fn get_cancel_progress(&self) -> Option<(u32, u32)> {
+        self.cancelling.as_ref()
+            .map(|cancellation| (cancellation.refunded, cancellation.total))
+    }
+
+    /// Takes a spam token out of contention and refunds everyone who voted
+    /// on it. The platform fee the creator paid and their bond are forfeited
+    /// to the treasury. Winners can only be flagged until `distribute`
+    /// settles them; they are taken off the winner lists, and their voters
+    /// also get the reward share their stake earned.
+    pub fn flag_spam(&mut self, token_id: TokenId) {
+        self.assert_admin();
+        let mut token = self.tokens.get(&token_id)
+            .expect("Token not found");
+        let unsettled_winner = token.status == TokenStatus::Winner && !self.is_settled(token_id);
+        assert!(
+            matches!(token.status, TokenStatus::Queued | TokenStatus::InVoting) || unsettled_winner,
+            "Token cannot be flagged"
+        );
+        token.status = TokenStatus::Removed;
+        self.tokens.insert(&token_id, &token);
+
+        let (reward, winning_stake, paid) = if unsettled_winner {
+            self.remove_winner(token_id, &token.creator);
+            self.drop_from_distribution(token_id)
+        } else {
+            (0, 0, 0)
+        };
+
+        if let Some(mut vote_info) = self.votes.remove(&token_id) {
+            let mut refunded = 0;
+            for (index, (voter, amount)) in vote_info.voters.iter().enumerate() {
+                // `distribute` already paid the first `paid` voters their
+                // share, and their stake too under `refund_winner_stakes`
+                let already_paid = (index as u64) < paid;
+                let share = if already_paid || reward == 0 {
+                    0
+                } else {
+                    Math::calculate_share(self.voting_mode.power(amount), winning_stake, reward)
+                };
+                if share > 0 {
+                    Promise::new(voter.clone()).transfer(share);
+                }
+                if !(already_paid && self.refund_winner_stakes) {
+                    self.transfer_stake(voter, amount);
+                }
+                refunded += amount;
+            }
+            if let Some(ref mut block) = self.current_block {
+                if block.tokens.contains(&token_id) {
+                    block.total_stakes -= refunded;
+                }
+            }
+            vote_info.voters.clear();
+        }
+
+        self.settle_bond(token_id, &token.creator, false);
+        if token.platform_fee_paid > 0 {
+            Promise::new(self.treasury_id.clone()).transfer(token.platform_fee_paid);
+        }
+    }
+
+    // Takes a flagged winner out of `winners` and its creator's wins. It is
+    // one of the last block's winners, so it sits near the end of `winners`
+    // and only the entries after it are moved.
+    fn remove_winner(&mut self, token_id: TokenId, creator: &AccountId) {
+        let mut later = Vec::new();
+        while let Some(winner) = self.winners.pop() {
+            if winner == token_id {
+                break;
+            }
+            later.push(winner);
+        }
+        for winner in later.into_iter().rev() {
+            self.winners.push(&winner);
+        }
+
+        if let Some(mut wins) = self.creator_wins.get(creator) {
+            wins.retain(|&id| id != token_id);
+            if wins.is_empty() {
+                self.creator_wins.remove(creator);
+            } else {
+                self.creator_wins.insert(creator, &wins);
+            }
+        }
+    }
+
+    // Records a flagged winner as a loser in the pending distribution, so it
+    // is never settled. Returns the distribution's reward and winning stake,
+    // and how many of the token's voters it has already paid.
+    fn drop_from_distribution(&mut self, token_id: TokenId) -> (Balance, Balance, u64) {
+        let distribution = match self.pending_distribution.as_mut() {
+            Some(distribution) => distribution,
+            None => return (0, 0, 0),
+        };
+        let index = match distribution.results.iter().position(|&(id, _)| id == token_id) {
+            Some(index) => index,
+            None => return (0, 0, 0),
+        };
+        distribution.results[index].1 = false;
+        let paid = if index == distribution.token_index as usize {
+            distribution.voter_index
+        } else {
+            0
+        };
+        (distribution.reward, distribution.winning_stake, paid)
+    }
+
+    // Admin function to switch between linear and quadratic voting. Only
+    // allowed between blocks so every vote in a block uses the same mode.
+    pub fn set_voting_mode(&mut self, mode: VotingMode) {
+        self.assert_owner();
+        assert!(self.current_block.is_none(), "Cannot change voting mode during a block");
+        assert!(
+            self.pending_distribution.is_none(),
+            "Previous block still being distributed"
+        );
+        self.voting_mode = mode;
+    }
+
+    // Admin function to choose how ties at the winner cutoff are broken
+    pub fn set_tie_break(&mut self, tie_break: TieBreak) {
+        self.assert_owner();
+        self.tie_break = tie_break;
+    }
+
+    // View methods
+    /// Losing-token voters of the finalized block not yet refunded
+    pub fn get_pending_refund_count(&self) -> u32 {
+        let distribution = match self.pending_distribution {
+            Some(ref distribution) => distribution,
+            None => return 0,
+        };
+        let mut pending = 0;
+        for (index, &(token_id, is_winner)) in distribution.results.iter().enumerate() {
+            let index = index as u64;
+            if is_winner || index < distribution.token_index {
+                continue;
+            }
+            let voter_count = self.votes.get(&token_id)
+                .map(|v| v.voters.len())
+                .unwrap_or(0);
+            pending += if index == distribution.token_index {
+                voter_count.saturating_sub(distribution.voter_index)
+            } else {
+                voter_count
+            };
+        }
+        pending as u32
+    }
+
+    /// How many `distribute` calls of `batch_size` items it takes to settle
+    /// the finalized block, or the current block if it were finalized now.
+    /// Counts from where a distribution in progress left off.
+    pub fn get_finalize_batch_count(&self, batch_size: u32) -> u32 {
+        assert!(batch_size > 0, "Batch size must be positive");
+        let (results, mut token_index, mut voter_index, reward) = match (&self.pending_distribution, &self.current_block) {
+            (Some(distribution), _) => (
+                distribution.results.clone(),
+                distribution.token_index as usize,
+                distribution.voter_index,
+                distribution.reward,
+            ),
+            (None, Some(block)) => {
+                let winners = self.compute_winners(block);
+                let winning_stake: Balance = winners.iter()
+                    .filter_map(|token_id| self.votes.get(token_id))
+                    .map(|v| v.total_votes - v.bonus_votes)
+                    .sum();
+                let reward = if winning_stake > 0 { self.voting_reward_pool } else { 0 };
+                let results = block.tokens.iter()
+                    .map(|token_id| (*token_id, winners.contains(token_id)))
+                    .collect();
+                (results, 0, 0, reward)
+            }
+            (None, None) => return 0,
+        };
+        let voter_counts: Vec<u64> = results.iter()
+            .map(|(token_id, _)| self.votes.get(token_id).map(|v| v.voters.len()).unwrap_or(0))
+            .collect();
+        let pays_winner_voters = reward > 0 || self.refund_winner_stakes;
+
+        // Walks the items the same way `distribute` does, a batch at a time
+        let mut batches = 0;
+        loop {
+            batches += 1;
+            let mut processed = 0;
+            while processed < batch_size as u64 && token_index < results.len() {
+                let (is_winner, voter_count) = (results[token_index].1, voter_counts[token_index]);
+                if is_winner && (!pays_winner_voters || voter_index >= voter_count) {
+                    token_index += 1;
+                    voter_index = 0;
+                    processed += 1;
+                } else if voter_index < voter_count {
+                    let step = (voter_count - voter_index).min(batch_size as u64 - processed);
+                    voter_index += step;
+                    processed += step;
+                } else {
+                    token_index += 1;
+                    voter_index = 0;
+                }
+            }
+            if token_index >= results.len() {
+                return batches;
+            }
+        }
+    }
+
+    /// Votes that `amount` of stake buys under the current voting mode
+    pub fn get_voting_power(&self, amount: U128) -> U128 {
+        U128(self.voting_mode.power(amount.0))
+    }
+
+    pub fn get_pending_refund(&self, account_id: AccountId) -> Option<PendingRefund> {
+        self.pending_refunds.get(&account_id)
+    }
+
+    pub fn get_reward_pool(&self) -> U128 {
+        U128(self.voting_reward_pool)
+    }
+
+    /// Every token that has won a block, oldest win first
+    pub fn get_all_winners(&self, from_index: u64, limit: u64) -> Vec<TokenView> {
+        (from_index..std::cmp::min(from_index + limit, self.winners.len()))
+            .filter_map(|index| self.winners.get(index))
+            .filter_map(|token_id| self.tokens.get(&token_id))
+            .map(|token| (&token).into())
+            .collect()
+    }
+
+    pub fn get_total_staked(&self) -> Option<U128> {
+        self.current_block.as_ref()
+            .map(|block| U128(block.total_stakes))
+    }
+
+    pub fn get_current_standings(&self) -> Vec<(TokenId, U128)> {
+        self.assert_standings_visible();
+        self.current_block.as_ref()
+            .map(|block| {
+                self.rank_tokens(block)
+                    .into_iter()
+                    .map(|(token_id, votes)| (token_id, U128(votes)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_winning_cutoff(&self) -> Option<U128> {
+        self.assert_standings_visible();
+        let block = self.current_block.as_ref()?;
+        let max_winners = block.max_winners as usize;
+        if max_winners == 0 {
+            return None;
+        }
+
+        let token_votes = self.rank_tokens(block);
+        token_votes.get(max_winners - 1)
+            .map(|(_, votes)| U128(*votes))
+    }
+
+    /// Additional votes `token_id` needs to pass the token now holding
+    /// `target_rank` (1 = first place) in the live standings; zero when
+    /// it already ranks there or higher
+    pub fn votes_needed_for_rank(&self, token_id: TokenId, target_rank: u32) -> U128 {
+        self.assert_standings_visible();
+        assert!(target_rank > 0, "Ranks start at 1");
+        let block = self.current_block.as_ref().expect("No active block");
+        let token_votes = self.rank_tokens(block);
+        let position = token_votes.iter()
+            .position(|(id, _)| *id == token_id)
+            .expect("Token not in current block");
+        if position < target_rank as usize {
+            return U128(0);
+        }
+
+        let own_votes = token_votes[position].1;
+        let target_votes = token_votes[target_rank as usize - 1].1;
+        U128(target_votes - own_votes + 1)
+    }
+
+    /// Winner slots not firmly claimed: `max_winners` minus the tokens
+    /// strictly above the winning cutoff (or with any votes, when there
+    /// are fewer tokens than slots)
+    pub fn get_remaining_winner_slots(&self) -> Option<u8> {
+        self.assert_standings_visible();
+        let block = self.current_block.as_ref()?;
+        let cutoff = self.get_winning_cutoff().map(|votes| votes.0).unwrap_or(0);
+        let claimed = self.rank_tokens(block)
+            .iter()
+            .filter(|(_, votes)| *votes > cutoff)
+            .count();
+        Some((block.max_winners as usize).saturating_sub(claimed) as u8)
+    }
+
+    /// Stake the account would get back if voting ended now: its votes on
+    /// every block token currently projected to lose, or on every block
+    /// token under `refund_winner_stakes`
+    pub fn get_refundable(&self, account_id: AccountId) -> U128 {
+        self.assert_standings_visible();
+        let block = match self.current_block.as_ref() {
+            Some(block) => block,
+            None => return U128(0),
+        };
+
+        let winners = self.compute_winners(block);
+        let refundable = block.tokens.iter()
+            .filter(|token_id| self.refund_winner_stakes || !winners.contains(token_id))
+            .filter_map(|token_id| self.votes.get(token_id))
+            .filter_map(|vote_info| vote_info.voters.get(&account_id))
+            .sum();
+
+        U128(refundable)
+    }
+
+    // Top `max_winners` tokens of the block, ties resolved per `tie_break`
+    pub(crate) fn compute_winners(&self, block: &Block) -> Vec<TokenId> {
+        let mut token_votes = self.rank_tokens(block);
+        let max_winners = block.max_winners as usize;
+
+        if self.tie_break == TieBreak::Random && max_winners > 0 && token_votes.len() > max_winners {
+            // Only the run of tokens tied with the last winning slot is shuffled
+            let cutoff = token_votes[max_winners - 1].1;
+            let start = token_votes.iter().position(|(_, votes)| *votes == cutoff).unwrap();
+            let end = token_votes.iter().rposition(|(_, votes)| *votes == cutoff).unwrap() + 1;
+
+            let seed = env::random_seed();
+            let tied = &mut token_votes[start..end];
+            for i in (1..tied.len()).rev() {
+                let j = seed[i % seed.len()] as usize % (i + 1);
+                tied.swap(i, j);
+            }
+        }
+
+        token_votes.iter()
+            .take(max_winners)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    // Block tokens ordered by total votes, highest first; equal votes keep
+    // the older (lower id) token ahead. Removed tokens are left out.
+    fn rank_tokens(&self, block: &Block) -> Vec<(TokenId, Balance)> {
+        let mut token_votes: Vec<(TokenId, Balance)> = block.tokens.iter()
+            .filter(|&token_id| {
+                self.tokens.get(token_id)
+                    .map_or(false, |token| token.status != TokenStatus::Removed)
+            })
+            .map(|&token_id| {
+                let votes = self.votes.get(&token_id)
+                    .map(|v| v.total_votes)
+                    .unwrap_or(0);
+                (token_id, votes)
+            })
+            .collect();
+
+        token_votes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        token_votes
+    }
+
+    // Pays stake back in whatever asset it was deposited in
+    pub(crate) fn transfer_stake(&self, receiver_id: AccountId, amount: Balance) -> Promise {
+        match self.stake_token {
+            Some(ref stake_token) => Promise::new(stake_token.clone()).function_call(
+                "ft_transfer".to_string(),
+                json!({ "receiver_id": receiver_id, "amount": U128(amount) })
+                    .to_string()
+                    .into_bytes(),
+                1,
+                GAS_FOR_FT_TRANSFER,
+            ),
+            None => Promise::new(receiver_id).transfer(amount),
+        }
+    }
+
+    // Tells `result_callback`, if set, which tokens won. Fire-and-forget:
+    // a failing receiver is only logged, finalization still stands
+    fn notify_result_callback(&self, winners: Vec<TokenId>) {
+        if let Some(receiver) = self.result_callback.clone() {
+            Promise::new(receiver.clone())
+                .function_call(
+                    "on_block_finalized".to_string(),
+                    json!({ "winners": winners }).to_string().into_bytes(),
+                    0,
+                    GAS_FOR_BLOCK_FINALIZED,
+                )
+                .then(Promise::new(env::current_account_id()).function_call(
+                    "on_result_callback_resolved".to_string(),
+                    json!({ "receiver": receiver, "winners": winners }).to_string().into_bytes(),
+                    0,
+                    GAS_FOR_RESULT_CALLBACK_RESOLVED,
+                ));
+        }
+    }
+
+    /// Logs a `result_callback_failed` event when the receiver of
+    /// `on_block_finalized` failed. Nothing is retried.
+    #[private]
+    pub fn on_result_callback_resolved(&mut self, receiver: AccountId, winners: Vec<TokenId>) {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            Events::result_callback_failed(&receiver, &winners);
+        }
+    }
+
+    // Asks `identity_verifier` whether `account_id` is verified, continuing
+    // with `callback` on this contract once it answers
+    pub(crate) fn verify_identity(&self, account_id: &AccountId, callback: &str, args: Value) -> Promise {
+        let verifier = self.identity_verifier.clone()
+            .expect("No identity verifier set");
+        Promise::new(verifier)
+            .function_call(
+                "is_verified".to_string(),
+                json!({ "account_id": account_id }).to_string().into_bytes(),
+                0,
+                GAS_FOR_IS_VERIFIED,
+            )
+            .then(Promise::new(env::current_account_id()).function_call(
+                callback.to_string(),
+                args.to_string().into_bytes(),
+                0,
+                GAS_FOR_VERIFIED_CALLBACK,
+            ))
+    }
+
+    // Whether the verifier answered `true`; failures and anything else
+    // count as unverified
+    pub(crate) fn identity_verified() -> bool {
+        match env::promise_result(0) {
+            PromiseResult::Successful(result) => {
+                near_sdk::serde_json::from_slice::<bool>(&result).unwrap_or(false)
+            }
+            _ => false,
+        }
     }
 
     // Helper methods
@@ -106,6 +1129,22 @@ impl TokenBlocks {
         );
     }
 
+    // Whether the block is in the last `standings_blackout_ns` of voting
+    pub(crate) fn in_standings_blackout(&self) -> bool {
+        self.current_block.as_ref()
+            .map(|block| {
+                let now = env::block_timestamp();
+                self.standings_blackout_ns > 0
+                    && block.is_voting_phase(now)
+                    && now + self.standings_blackout_ns >= block.voting_end_time
+            })
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn assert_standings_visible(&self) {
+        assert!(!self.in_standings_blackout(), "Standings hidden until voting ends");
+    }
+
     fn is_voting_phase_ended(&self) -> bool {
         if let Some(block) = &self.current_block {
             let voting_end_time = block.start_time + block.accepting_tokens_duration + block.voting_duration;
@@ -6,6 +6,9 @@ use near_sdk::MockedBlockchain;
 use token_blocks::TokenMetadata;
 use near_sdk::json_types::ValidAccountId;
 
+// Covers the platform fee and storage of one token
+const CREATION_DEPOSIT: Balance = 1_000_000_000_000_000_000_000_000; // 1 NEAR
+
 fn setup_voting_context(predecessor: &str, deposit: Balance) -> VMContextBuilder {
     let mut context = VMContextBuilder::new();
     context
@@ -18,14 +21,14 @@ fn setup_voting_context(predecessor: &str, deposit: Balance) -> VMContextBuilder
 
 #[test]
 fn test_voting() {
-    let mut context = setup_voting_context("owner.near", 0);
+    let mut context = setup_voting_context("owner.near", CREATION_DEPOSIT);
     testing_env!(context.build());
 
     let mut contract = TokenBlocks::new("owner.near".to_string());
 
     // Create and start a block first
-    let token_id = contract.create_token(create_test_metadata());
-    contract.start_block();
+    let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+    contract.start_block(None, None, None);
     
     // Verify block is active
     assert!(contract.get_current_block().is_some());
@@ -49,14 +52,14 @@ fn test_voting() {
 #[test]
 #[should_panic(expected = "Stake too low")]
 fn test_vote_with_low_stake() {
-    let mut context = setup_voting_context("owner.near", 0);
+    let mut context = setup_voting_context("owner.near", CREATION_DEPOSIT);
     testing_env!(context.build());
 
     let mut contract = TokenBlocks::new("owner.near".to_string());
     
     // Create and start a block first
-    let token_id = contract.create_token(create_test_metadata());
-    contract.start_block();
+    let token_id = contract.create_token("ipfs://content".to_string(), create_test_metadata(), None, None);
+    contract.start_block(None, None, None);
     
     // Advance time to voting phase and update phase
     context.block_timestamp(ACCEPTING_TOKENS_DURATION + 1);
@@ -81,5 +84,6 @@ fn create_test_metadata() -> TokenMetadata {
         expires_at: None,
         starts_at: None,
         extra: None,
+        symbol: None,
     }
 }